@@ -17,330 +17,2314 @@ pub mod aoc {
             .collect()
     }
 
+    // A blank-line-separated record. `number` and the line numbers are 1-based.
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    pub struct Record {
+        pub number: usize,
+        pub lines: Vec<(usize, String)>,
+    }
+
+    impl Record {
+        pub fn first_line(&self) -> usize {
+            self.lines[0].0
+        }
+
+        pub fn last_line(&self) -> usize {
+            self.lines[self.lines.len() - 1].0
+        }
+
+        pub fn text(&self) -> String {
+            self.lines
+                .iter()
+                .map(|l| l.1.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+
+    // Splits on runs of blank lines. Line endings may be `\n` or `\r\n`, lines holding only
+    // whitespace count as blank and the remaining lines are trimmed.
+    pub fn records(contents: &str) -> Vec<Record> {
+        let mut records: Vec<Record> = vec![];
+        let mut current = vec![];
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                if !current.is_empty() {
+                    records.push(Record {
+                        number: records.len() + 1,
+                        lines: current,
+                    });
+                    current = vec![];
+                }
+            } else {
+                current.push((i + 1, line.to_string()));
+            }
+        }
+        if !current.is_empty() {
+            records.push(Record {
+                number: records.len() + 1,
+                lines: current,
+            });
+        }
+        records
+    }
+
+    pub fn records_from_file(filename: impl AsRef<Path>) -> Vec<Record> {
+        let contents = fs::read_to_string(filename).expect("Could not read file.");
+        records(&contents)
+    }
+
+    // Quotes a CSV field when it contains a separator, quote or line break.
+    fn csv_field(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn json_string(value: &str) -> String {
+        let mut escaped = String::from("\"");
+        for c in value.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                c => escaped.push(c),
+            }
+        }
+        escaped.push('"');
+        escaped
+    }
+
+    // Reads a flat JSON object whose values are strings, numbers, booleans or null. Values
+    // other than null are kept as their text; null members are dropped.
+    fn json_object(text: &str) -> Result<Vec<(String, String)>, String> {
+        let mut chars = text.trim().chars().peekable();
+        let mut members = vec![];
+        let skip_whitespace = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+        };
+        if chars.next() != Some('{') {
+            return Err("expected `{`".to_string());
+        }
+        skip_whitespace(&mut chars);
+        if chars.peek() == Some(&'}') {
+            chars.next();
+        } else {
+            loop {
+                skip_whitespace(&mut chars);
+                let key = json_string_literal(&mut chars)?;
+                skip_whitespace(&mut chars);
+                if chars.next() != Some(':') {
+                    return Err(format!("expected `:` after {}", json_string(&key)));
+                }
+                skip_whitespace(&mut chars);
+                let value = if chars.peek() == Some(&'"') {
+                    Some(json_string_literal(&mut chars)?)
+                } else {
+                    let mut literal = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c == ',' || c == '}' || c.is_whitespace() {
+                            break;
+                        }
+                        literal.push(c);
+                        chars.next();
+                    }
+                    let is_number = literal.parse::<f64>().is_ok()
+                        && literal
+                            .chars()
+                            .all(|c| "+-.eE".contains(c) || c.is_ascii_digit());
+                    match literal.as_str() {
+                        "null" => None,
+                        "true" | "false" => Some(literal),
+                        _ if is_number => Some(literal),
+                        _ => return Err(format!("unexpected value `{}`", literal)),
+                    }
+                };
+                if let Some(value) = value {
+                    members.push((key, value));
+                }
+                skip_whitespace(&mut chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => break,
+                    _ => return Err("expected `,` or `}`".to_string()),
+                }
+            }
+        }
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(members),
+            Some(c) => Err(format!("unexpected `{}` after the object", c)),
+        }
+    }
+
+    fn json_string_literal(
+        chars: &mut std::iter::Peekable<std::str::Chars>,
+    ) -> Result<String, String> {
+        if chars.next() != Some('"') {
+            return Err("expected a string".to_string());
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                None => return Err("unterminated string".to_string()),
+                Some('"') => return Ok(value),
+                Some('\\') => match chars.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("bad escape `\\u{}`", hex))?;
+                        value.push(c);
+                    }
+                    other => return Err(format!("bad escape `\\{}`", other.unwrap_or(' '))),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    // Splits CSV text into rows, each paired with the 1-based line it starts on. Quoted fields
+    // may hold separators, doubled quotes and line breaks. Blank lines are skipped.
+    fn csv_rows(contents: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+        let mut rows = vec![];
+        let mut chars = contents.chars().peekable();
+        let mut line = 1;
+        while chars.peek().is_some() {
+            let start = line;
+            let mut row = vec![];
+            let mut field = String::new();
+            let mut quoted = false;
+            loop {
+                match chars.next() {
+                    None if quoted => {
+                        return Err(format!("line {}: unterminated quoted field", start))
+                    }
+                    Some('"') if quoted => {
+                        if chars.peek() == Some(&'"') {
+                            chars.next();
+                            field.push('"');
+                        } else {
+                            quoted = false;
+                        }
+                    }
+                    Some(c) if quoted => {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        field.push(c);
+                    }
+                    Some('"') if field.is_empty() => quoted = true,
+                    Some(',') => row.push(std::mem::take(&mut field)),
+                    Some('\r') if chars.peek() == Some(&'\n') => (),
+                    None | Some('\n') => {
+                        line += 1;
+                        row.push(field);
+                        break;
+                    }
+                    Some(c) => field.push(c),
+                }
+            }
+            if row.len() > 1 || !row[0].trim().is_empty() {
+                rows.push((start, row));
+            }
+        }
+        Ok(rows)
+    }
+
     pub mod day_one {
+        use std::collections::HashMap;
+        use std::fs;
+
+        const TARGET: i32 = 2020;
+
+        // Entries are listed in the order they appear in the report, alongside their indices.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct Solution {
+            pub entries: Vec<i32>,
+            pub indices: Vec<usize>,
+        }
+
+        pub fn solve(problem: super::Problem, file: &str) -> Option<i32> {
+            let subset_size = match problem {
+                super::Problem::One => 2,
+                super::Problem::Two => 3,
+            };
+
+            let contents = fs::read_to_string(file).expect("Something went wrong reading the file");
+            let entries: Vec<i32> = contents
+                .split_whitespace()
+                .map(|s| s.parse().expect("parse error"))
+                .collect();
+
+            Some(
+                k_sum(&entries, subset_size, TARGET)?
+                    .entries
+                    .iter()
+                    .product(),
+            )
+        }
+
+        // Finds `k` distinct entries (by position, so a repeated value may be used once per
+        // occurrence) that add up to `target`.
+        pub fn k_sum(entries: &[i32], k: usize, target: i32) -> Option<Solution> {
+            let mut indices = match k {
+                0 => {
+                    if target == 0 {
+                        vec![]
+                    } else {
+                        return None;
+                    }
+                }
+                1 => vec![entries.iter().position(|&e| e == target)?],
+                2 => two_sum_hashed(entries, target as i64)?,
+                _ => {
+                    let mut order: Vec<usize> = (0..entries.len()).collect();
+                    order.sort_by_key(|&i| entries[i]);
+                    k_sum_sorted(entries, &order, k, target as i64)?
+                }
+            };
+            indices.sort_unstable();
+            Some(Solution {
+                entries: indices.iter().map(|&i| entries[i]).collect(),
+                indices,
+            })
+        }
+
+        fn two_sum_hashed(entries: &[i32], target: i64) -> Option<Vec<usize>> {
+            let mut seen: HashMap<i64, usize> = HashMap::new();
+            for (j, &entry) in entries.iter().enumerate() {
+                if let Some(&i) = seen.get(&(target - entry as i64)) {
+                    return Some(vec![i, j]);
+                }
+                seen.entry(entry as i64).or_insert(j);
+            }
+            None
+        }
+
+        // `order` holds indices into `entries`, sorted by value. For k = 2 the two pointers
+        // close in from either end; larger k fixes the smallest entry and recurses.
+        fn k_sum_sorted(
+            entries: &[i32],
+            order: &[usize],
+            k: usize,
+            target: i64,
+        ) -> Option<Vec<usize>> {
+            if order.len() < k {
+                return None;
+            }
+            if k == 2 {
+                let (mut low, mut high) = (0, order.len() - 1);
+                while low < high {
+                    let sum = entries[order[low]] as i64 + entries[order[high]] as i64;
+                    match sum.cmp(&target) {
+                        std::cmp::Ordering::Less => low += 1,
+                        std::cmp::Ordering::Greater => high -= 1,
+                        std::cmp::Ordering::Equal => return Some(vec![order[low], order[high]]),
+                    }
+                }
+                return None;
+            }
+            for first in 0..=order.len() - k {
+                if first > 0 && entries[order[first]] == entries[order[first - 1]] {
+                    continue;
+                }
+                let rest = target - entries[order[first]] as i64;
+                if let Some(mut indices) = k_sum_sorted(entries, &order[first + 1..], k - 1, rest) {
+                    indices.push(order[first]);
+                    return Some(indices);
+                }
+            }
+            None
+        }
+
+        // Subset-sum tables only work for non-negative entries. A negative target has no
+        // subsets at all.
+        fn check_subset_input(entries: &[i32], target: i32) -> Result<Option<usize>, String> {
+            if let Some(entry) = entries.iter().find(|&&e| e < 0) {
+                return Err(format!(
+                    "subset sums need non-negative entries, not {}",
+                    entry
+                ));
+            }
+            Ok(if target < 0 {
+                None
+            } else {
+                Some(target as usize)
+            })
+        }
+
+        // The most cells a subset-sum table may hold. Tables grow with the target, so a large
+        // target is refused rather than exhausting memory.
+        pub const MAX_SUBSET_TABLE_CELLS: usize = 1 << 24;
+
+        fn check_table_size(dimensions: &[usize]) -> Result<(), String> {
+            match dimensions
+                .iter()
+                .try_fold(1usize, |cells, &d| cells.checked_mul(d))
+            {
+                Some(cells) if cells <= MAX_SUBSET_TABLE_CELLS => Ok(()),
+                _ => Err(format!(
+                    "a subset-sum table holds at most {} cells",
+                    MAX_SUBSET_TABLE_CELLS
+                )),
+            }
+        }
+
+        // `size` restricts the subsets to exactly that many entries; `None` allows any size.
+        pub fn count_subsets(
+            entries: &[i32],
+            target: i32,
+            size: Option<usize>,
+        ) -> Result<u128, String> {
+            let target = match check_subset_input(entries, target)? {
+                Some(t) => t,
+                None => return Ok(0),
+            };
+            // ways[k][s]: number of subsets seen so far with k entries (or any number when
+            // `size` is `None`) summing to s.
+            let sizes = size.map_or(1, |k| k.saturating_add(1));
+            check_table_size(&[sizes, target + 1])?;
+            let mut ways: Vec<Vec<u128>> = vec![vec![0; target + 1]; sizes];
+            ways[0][0] = 1;
+            for &entry in entries {
+                let entry = entry as usize;
+                if entry > target {
+                    continue;
+                }
+                for k in (0..sizes).rev() {
+                    let from = if size.is_some() {
+                        if k == 0 {
+                            continue;
+                        }
+                        k - 1
+                    } else {
+                        k
+                    };
+                    for sum in (entry..=target).rev() {
+                        let add = ways[from][sum - entry];
+                        ways[k][sum] = ways[k][sum]
+                            .checked_add(add)
+                            .ok_or_else(|| "the number of subsets overflows u128".to_string())?;
+                    }
+                }
+            }
+            Ok(ways[sizes - 1][target])
+        }
+
+        // Yields every matching subset. A reachability table built from the back of the list
+        // prunes every branch that cannot be completed, so the work is proportional to the
+        // number of solutions rather than to 2^n. `size` works as in `count_subsets`.
+        pub fn subsets(
+            entries: &[i32],
+            target: i32,
+            size: Option<usize>,
+        ) -> Result<Subsets<'_>, String> {
+            let n = entries.len();
+            let sizes = size.map_or(1, |k| k.saturating_add(1));
+            let target = check_subset_input(entries, target)?;
+            let t = target.unwrap_or(0);
+            check_table_size(&[n + 1, sizes, t + 1])?;
+            // reachable[i][k][s]: entries[i..] contain a subset of k entries (any number when
+            // `size` is `None`) summing to s.
+            let mut reachable = vec![vec![vec![false; t + 1]; sizes]; n + 1];
+            reachable[n][0][0] = true;
+            for i in (0..n).rev() {
+                let entry = entries[i] as usize;
+                for k in 0..sizes {
+                    for sum in 0..=t {
+                        let skip = reachable[i + 1][k][sum];
+                        let take = sum >= entry
+                            && match size {
+                                Some(_) => k > 0 && reachable[i + 1][k - 1][sum - entry],
+                                None => reachable[i + 1][k][sum - entry],
+                            };
+                        reachable[i][k][sum] = skip || take;
+                    }
+                }
+            }
+
+            let mut stack = vec![];
+            if let Some(t) = target {
+                if reachable[0][sizes - 1][t] {
+                    stack.push((0, sizes - 1, t, vec![]));
+                }
+            }
+            Ok(Subsets {
+                entries,
+                counts_size: size.is_some(),
+                reachable,
+                stack,
+            })
+        }
+
+        pub fn any_subset(entries: &[i32], target: i32) -> Option<Solution> {
+            subsets(entries, target, None).ok()?.next()
+        }
+
+        #[derive(Debug, Clone)]
+        pub struct Subsets<'a> {
+            entries: &'a [i32],
+            counts_size: bool,
+            reachable: Vec<Vec<Vec<bool>>>,
+            // (next index, entries still to pick, sum still needed, indices picked so far)
+            stack: Vec<(usize, usize, usize, Vec<usize>)>,
+        }
+
+        impl<'a> Iterator for Subsets<'a> {
+            type Item = Solution;
+
+            fn next(&mut self) -> Option<Solution> {
+                while let Some((i, k, sum, chosen)) = self.stack.pop() {
+                    if i == self.entries.len() {
+                        return Some(Solution {
+                            entries: chosen.iter().map(|&c| self.entries[c]).collect(),
+                            indices: chosen,
+                        });
+                    }
+                    if self.reachable[i + 1][k][sum] {
+                        self.stack.push((i + 1, k, sum, chosen.clone()));
+                    }
+                    let entry = self.entries[i] as usize;
+                    let next_k = if self.counts_size {
+                        k.checked_sub(1)
+                    } else {
+                        Some(k)
+                    };
+                    if let Some(next_k) = next_k {
+                        if sum >= entry && self.reachable[i + 1][next_k][sum - entry] {
+                            let mut chosen = chosen;
+                            chosen.push(i);
+                            self.stack.push((i + 1, next_k, sum - entry, chosen));
+                        }
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    pub mod day_two {
+        use std::fs::File;
+        use std::io::BufRead;
+        use std::io::BufReader;
+
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct PasswordInfo {
+            pub range: (i32, i32),
+            pub letter: char,
+            pub password: String,
+        }
+
+        impl PasswordInfo {
+            pub fn new(line: &str) -> Self {
+                PasswordInfo::parse(line).expect("Could not parse password line.")
+            }
+
+            pub fn parse(line: &str) -> Result<Self, String> {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() != 3 {
+                    return Err(format!("expected 3 fields, found {}", fields.len()));
+                }
+                let range = PasswordInfo::parse_range(fields[0])?;
+                let letter = match fields[1].strip_suffix(':') {
+                    Some(letter) if letter.chars().count() == 1 => {
+                        letter.chars().next().expect("Letter is one character.")
+                    }
+                    _ => return Err(format!("could not parse letter `{}`", fields[1])),
+                };
+                let password = fields[2].to_string();
+                Ok(PasswordInfo {
+                    range,
+                    letter,
+                    password,
+                })
+            }
+
+            fn parse_range(range: &str) -> Result<(i32, i32), String> {
+                let (min, max) = range
+                    .split_once('-')
+                    .ok_or_else(|| format!("could not parse range `{}`", range))?;
+                let min = min
+                    .parse()
+                    .map_err(|_| format!("could not parse range minimum `{}`", min))?;
+                let max = max
+                    .parse()
+                    .map_err(|_| format!("could not parse range maximum `{}`", max))?;
+                Ok((min, max))
+            }
+
+            fn letter_count(&self) -> i32 {
+                self.password.matches(self.letter).count() as i32
+            }
+
+            // Positions are 1-based; anything outside the password never holds the letter.
+            fn letter_at(&self, position: i32) -> bool {
+                position >= 1
+                    && self.password.chars().nth(position as usize - 1) == Some(self.letter)
+            }
+
+            fn positions_out_of_range(&self) -> Option<String> {
+                let len = self.password.chars().count() as i32;
+                [self.range.0, self.range.1]
+                    .iter()
+                    .find(|&&p| p < 1 || p > len)
+                    .map(|p| format!("position {} out of range", p))
+            }
+        }
+
+        pub trait PasswordPolicy {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool;
+            // The spec that `parse_policy` turns back into this policy.
+            fn name(&self) -> String;
+
+            // Says why an entry fails the policy.
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                if self.is_valid(entry) {
+                    Ok(())
+                } else {
+                    Err(format!("fails {}", self.name()))
+                }
+            }
+        }
+
+        // Part one: the letter must appear between `min` and `max` times.
+        #[derive(Debug, Clone, Copy)]
+        pub struct CountRange;
+
+        impl PasswordPolicy for CountRange {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool {
+                (entry.range.0..=entry.range.1).contains(&entry.letter_count())
+            }
+
+            fn name(&self) -> String {
+                "count-range".to_string()
+            }
+
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                if self.is_valid(entry) {
+                    return Ok(());
+                }
+                Err(format!(
+                    "letter '{}' appears {} times, allowed {}-{}",
+                    entry.letter,
+                    entry.letter_count(),
+                    entry.range.0,
+                    entry.range.1
+                ))
+            }
+        }
+
+        // Part two: the letter must be at exactly one of the two positions.
+        #[derive(Debug, Clone, Copy)]
+        pub struct PositionalXor;
+
+        impl PasswordPolicy for PositionalXor {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool {
+                entry.letter_at(entry.range.0) != entry.letter_at(entry.range.1)
+            }
+
+            fn name(&self) -> String {
+                "positional-xor".to_string()
+            }
+
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                if self.is_valid(entry) {
+                    return Ok(());
+                }
+                if let Some(reason) = entry.positions_out_of_range() {
+                    return Err(reason);
+                }
+                let found = if entry.letter_at(entry.range.0) {
+                    "both"
+                } else {
+                    "neither"
+                };
+                Err(format!(
+                    "letter '{}' at {} of positions {} and {}",
+                    entry.letter, found, entry.range.0, entry.range.1
+                ))
+            }
+        }
+
+        #[derive(Debug, Clone, Copy)]
+        pub struct PositionalAnd;
+
+        impl PasswordPolicy for PositionalAnd {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool {
+                entry.letter_at(entry.range.0) && entry.letter_at(entry.range.1)
+            }
+
+            fn name(&self) -> String {
+                "positional-and".to_string()
+            }
+
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                if self.is_valid(entry) {
+                    return Ok(());
+                }
+                if let Some(reason) = entry.positions_out_of_range() {
+                    return Err(reason);
+                }
+                let missing = if entry.letter_at(entry.range.0) {
+                    entry.range.1
+                } else {
+                    entry.range.0
+                };
+                Err(format!(
+                    "letter '{}' not at position {}",
+                    entry.letter, missing
+                ))
+            }
+        }
+
+        // Written like a regex class with a quantifier, e.g. `[0-9]{1,}` or `[^a-z]{0}`: the
+        // number of password characters in the class must be within the bounds.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct CharacterClass {
+            negated: bool,
+            ranges: Vec<(char, char)>,
+            min: usize,
+            max: Option<usize>,
+            class: String,
+            spec: String,
+        }
+
+        impl CharacterClass {
+            pub fn parse(spec: &str) -> Result<CharacterClass, String> {
+                let body = spec
+                    .strip_prefix('[')
+                    .ok_or_else(|| format!("Character class `{}` must start with `[`.", spec))?;
+                let close = body
+                    .find(']')
+                    .ok_or_else(|| format!("Character class `{}` is missing `]`.", spec))?;
+                let (class, quantifier) = (&body[..close], &body[close + 1..]);
+                let (negated, class) = match class.strip_prefix('^') {
+                    Some(rest) => (true, rest),
+                    None => (false, class),
+                };
+
+                let chars: Vec<char> = class.chars().collect();
+                if chars.is_empty() {
+                    return Err(format!("Character class `{}` is empty.", spec));
+                }
+                let mut ranges = vec![];
+                let mut i = 0;
+                while i < chars.len() {
+                    if i + 2 < chars.len() && chars[i + 1] == '-' {
+                        if chars[i] > chars[i + 2] {
+                            return Err(format!(
+                                "Range `{}-{}` is backwards.",
+                                chars[i],
+                                chars[i + 2]
+                            ));
+                        }
+                        ranges.push((chars[i], chars[i + 2]));
+                        i += 3;
+                    } else {
+                        ranges.push((chars[i], chars[i]));
+                        i += 1;
+                    }
+                }
+
+                let (min, max) = if quantifier.is_empty() {
+                    (1, None)
+                } else {
+                    let bounds = quantifier
+                        .strip_prefix('{')
+                        .and_then(|q| q.strip_suffix('}'))
+                        .ok_or_else(|| {
+                            format!("Quantifier `{}` must look like `{{n,m}}`.", quantifier)
+                        })?;
+                    let parse = |n: &str| {
+                        n.trim()
+                            .parse::<usize>()
+                            .map_err(|_| format!("Bad quantifier bound `{}`.", n))
+                    };
+                    match bounds.split_once(',') {
+                        None => {
+                            let n = parse(bounds)?;
+                            (n, Some(n))
+                        }
+                        Some((low, high)) if high.trim().is_empty() => (parse(low)?, None),
+                        Some((low, high)) => (parse(low)?, Some(parse(high)?)),
+                    }
+                };
+                if let Some(max) = max.filter(|&max| max < min) {
+                    return Err(format!("Quantifier `{{{},{}}}` is backwards.", min, max));
+                }
+
+                Ok(CharacterClass {
+                    negated,
+                    ranges,
+                    min,
+                    max,
+                    class: spec[..close + 2].to_string(),
+                    spec: spec.to_string(),
+                })
+            }
+
+            fn contains(&self, c: char) -> bool {
+                self.ranges
+                    .iter()
+                    .any(|&(low, high)| (low..=high).contains(&c))
+                    != self.negated
+            }
+
+            fn count(&self, password: &str) -> usize {
+                password.chars().filter(|&c| self.contains(c)).count()
+            }
+        }
+
+        impl PasswordPolicy for CharacterClass {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool {
+                let count = self.count(&entry.password);
+                count >= self.min && self.max.is_none_or(|max| count <= max)
+            }
+
+            fn name(&self) -> String {
+                self.spec.clone()
+            }
+
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                if self.is_valid(entry) {
+                    return Ok(());
+                }
+                let allowed = match self.max {
+                    Some(max) if max == self.min => format!("{}", max),
+                    Some(max) => format!("{}-{}", self.min, max),
+                    None => format!("{} or more", self.min),
+                };
+                Err(format!(
+                    "{} characters match {}, allowed {}",
+                    self.count(&entry.password),
+                    self.class,
+                    allowed
+                ))
+            }
+        }
+
+        pub struct All(pub Vec<Box<dyn PasswordPolicy>>);
+
+        impl PasswordPolicy for All {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool {
+                self.0.iter().all(|p| p.is_valid(entry))
+            }
+
+            fn name(&self) -> String {
+                format!("and({})", join_names(&self.0))
+            }
+
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                let reasons: Vec<String> =
+                    self.0.iter().filter_map(|p| p.check(entry).err()).collect();
+                if reasons.is_empty() {
+                    Ok(())
+                } else {
+                    Err(reasons.join("; "))
+                }
+            }
+        }
+
+        pub struct Any(pub Vec<Box<dyn PasswordPolicy>>);
+
+        impl PasswordPolicy for Any {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool {
+                self.0.iter().any(|p| p.is_valid(entry))
+            }
+
+            fn name(&self) -> String {
+                format!("or({})", join_names(&self.0))
+            }
+
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                let mut reasons = vec![];
+                for policy in &self.0 {
+                    match policy.check(entry) {
+                        Ok(()) => return Ok(()),
+                        Err(reason) => reasons.push(reason),
+                    }
+                }
+                Err(format!("none of: {}", reasons.join("; ")))
+            }
+        }
+
+        pub struct Not(pub Box<dyn PasswordPolicy>);
+
+        impl PasswordPolicy for Not {
+            fn is_valid(&self, entry: &PasswordInfo) -> bool {
+                !self.0.is_valid(entry)
+            }
+
+            fn name(&self) -> String {
+                format!("not({})", self.0.name())
+            }
+
+            fn check(&self, entry: &PasswordInfo) -> Result<(), String> {
+                if self.is_valid(entry) {
+                    Ok(())
+                } else {
+                    Err(format!("passes {}", self.0.name()))
+                }
+            }
+        }
+
+        fn join_names(policies: &[Box<dyn PasswordPolicy>]) -> String {
+            policies
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<String>>()
+                .join(",")
+        }
+
+        // Accepts `count-range`, `positional-xor`, `positional-and`, a character class such as
+        // `[a-z]{8,}`, and `and(...)`, `or(...)` and `not(...)` around other specs.
+        pub fn parse_policy(spec: &str) -> Result<Box<dyn PasswordPolicy>, String> {
+            let spec = spec.trim();
+            match spec {
+                "count-range" => return Ok(Box::new(CountRange)),
+                "positional-xor" => return Ok(Box::new(PositionalXor)),
+                "positional-and" => return Ok(Box::new(PositionalAnd)),
+                _ => (),
+            }
+            if spec.starts_with('[') {
+                return Ok(Box::new(CharacterClass::parse(spec)?));
+            }
+
+            let open = spec
+                .find('(')
+                .ok_or_else(|| format!("Unknown password policy `{}`.", spec))?;
+            let args = spec[open + 1..]
+                .strip_suffix(')')
+                .ok_or_else(|| format!("Policy `{}` is missing a closing `)`.", spec))?;
+            let mut policies = split_arguments(args)?
+                .into_iter()
+                .map(parse_policy)
+                .collect::<Result<Vec<_>, _>>()?;
+            match &spec[..open] {
+                "and" if !policies.is_empty() => Ok(Box::new(All(policies))),
+                "or" if !policies.is_empty() => Ok(Box::new(Any(policies))),
+                "not" if policies.len() == 1 => Ok(Box::new(Not(policies.remove(0)))),
+                name => Err(format!(
+                    "Bad combinator `{}` with {} argument(s).",
+                    name,
+                    policies.len()
+                )),
+            }
+        }
+
+        // Splits on the commas that are not nested inside (), [] or {}.
+        fn split_arguments(args: &str) -> Result<Vec<&str>, String> {
+            let mut parts = vec![];
+            let mut depth = 0;
+            let mut start = 0;
+            for (i, c) in args.char_indices() {
+                match c {
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' | '}' => {
+                        if depth == 0 {
+                            return Err(format!("Unbalanced `{}` in `{}`.", c, args));
+                        }
+                        depth -= 1;
+                    }
+                    ',' if depth == 0 => {
+                        parts.push(&args[start..i]);
+                        start = i + 1;
+                    }
+                    _ => (),
+                }
+            }
+            if depth != 0 {
+                return Err(format!("Unbalanced brackets in `{}`.", args));
+            }
+            if !args.trim().is_empty() {
+                parts.push(&args[start..]);
+            }
+            Ok(parts)
+        }
+
+        pub fn count_valid(filename: &str, policy: &dyn PasswordPolicy) -> i32 {
+            let file = File::open(filename).expect("Could not open file.");
+            let reader = BufReader::new(file);
+
+            reader
+                .lines()
+                .map(|x| PasswordInfo::new(&x.expect("")))
+                .filter(|x| policy.is_valid(x))
+                .count() as i32
+        }
+
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct Verdict {
+            pub policy: String,
+            pub result: Result<(), String>,
+        }
+
+        // One line of the password database: the parsed entry (or why it could not be parsed)
+        // and its verdict under each policy.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct AuditLine {
+            pub line_number: usize,
+            pub entry: Result<PasswordInfo, String>,
+            pub verdicts: Vec<Verdict>,
+        }
+
+        pub fn audit(filename: &str, policies: &[Box<dyn PasswordPolicy>]) -> Vec<AuditLine> {
+            audit_lines(&super::lines_from_file(filename), policies)
+        }
+
+        pub fn audit_lines(
+            lines: &[String],
+            policies: &[Box<dyn PasswordPolicy>],
+        ) -> Vec<AuditLine> {
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let entry = PasswordInfo::parse(line);
+                    let verdicts = match &entry {
+                        Ok(entry) => policies
+                            .iter()
+                            .map(|p| Verdict {
+                                policy: p.name(),
+                                result: p.check(entry),
+                            })
+                            .collect(),
+                        Err(_) => vec![],
+                    };
+                    AuditLine {
+                        line_number: i + 1,
+                        entry,
+                        verdicts,
+                    }
+                })
+                .collect()
+        }
+
+        // One row per line, with a `valid`/`reason` column pair for each policy. The policy
+        // columns come from the verdicts of the first parsed line, and lines that could not be
+        // parsed leave them empty.
+        pub fn audit_to_csv(audit: &[AuditLine]) -> String {
+            let policies: Vec<&str> = audit
+                .iter()
+                .find(|line| line.entry.is_ok())
+                .map_or(vec![], |line| {
+                    line.verdicts.iter().map(|v| v.policy.as_str()).collect()
+                });
+            let mut header: Vec<String> = ["line", "min", "max", "letter", "password", "error"]
+                .iter()
+                .map(|h| h.to_string())
+                .collect();
+            for policy in &policies {
+                header.push(super::csv_field(policy));
+                header.push(super::csv_field(&format!("{} reason", policy)));
+            }
+            let mut csv = header.join(",") + "\n";
+
+            for line in audit {
+                let mut row = vec![line.line_number.to_string()];
+                match &line.entry {
+                    Ok(entry) => {
+                        row.push(entry.range.0.to_string());
+                        row.push(entry.range.1.to_string());
+                        row.push(super::csv_field(&entry.letter.to_string()));
+                        row.push(super::csv_field(&entry.password));
+                        row.push(String::new());
+                    }
+                    Err(error) => {
+                        row.extend(vec![String::new(); 4]);
+                        row.push(super::csv_field(error));
+                        row.extend(vec![String::new(); 2 * policies.len()]);
+                    }
+                }
+                for verdict in &line.verdicts {
+                    match &verdict.result {
+                        Ok(()) => row.extend(vec!["valid".to_string(), String::new()]),
+                        Err(reason) => {
+                            row.push("invalid".to_string());
+                            row.push(super::csv_field(reason));
+                        }
+                    }
+                }
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+
+        // A JSON array with one object per line.
+        pub fn audit_to_json(audit: &[AuditLine]) -> String {
+            let lines: Vec<String> = audit
+                .iter()
+                .map(|line| {
+                    let (entry, error) = match &line.entry {
+                        Ok(entry) => (
+                            format!(
+                                "{{\"min\":{},\"max\":{},\"letter\":{},\"password\":{}}}",
+                                entry.range.0,
+                                entry.range.1,
+                                super::json_string(&entry.letter.to_string()),
+                                super::json_string(&entry.password)
+                            ),
+                            "null".to_string(),
+                        ),
+                        Err(error) => ("null".to_string(), super::json_string(error)),
+                    };
+                    let verdicts: Vec<String> = line
+                        .verdicts
+                        .iter()
+                        .map(|v| {
+                            let reason = match &v.result {
+                                Ok(()) => "null".to_string(),
+                                Err(reason) => super::json_string(reason),
+                            };
+                            format!(
+                                "{{\"policy\":{},\"valid\":{},\"reason\":{}}}",
+                                super::json_string(&v.policy),
+                                v.result.is_ok(),
+                                reason
+                            )
+                        })
+                        .collect();
+                    format!(
+                        "{{\"line\":{},\"entry\":{},\"error\":{},\"verdicts\":[{}]}}",
+                        line.line_number,
+                        entry,
+                        error,
+                        verdicts.join(",")
+                    )
+                })
+                .collect();
+            format!("[{}]", lines.join(",\n"))
+        }
+
+        pub fn solve(problem: super::Problem, file: &str) -> i32 {
+            match problem {
+                super::Problem::One => count_valid(file, &CountRange),
+                super::Problem::Two => count_valid(file, &PositionalXor),
+            }
+        }
+    }
+
+    pub mod day_three {
+
+        // `right` may be negative for leftward moves. A slope with `down` of 0 never leaves the
+        // top row, so it lands on no cells.
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+        pub struct Slope {
+            pub right: i64,
+            pub down: usize,
+        }
+
+        pub const PART_TWO_SLOPES: [Slope; 5] = [
+            Slope { right: 1, down: 1 },
+            Slope { right: 3, down: 1 },
+            Slope { right: 5, down: 1 },
+            Slope { right: 7, down: 1 },
+            Slope { right: 1, down: 2 },
+        ];
+
+        // (row, column) of every cell landed on after the start, and the subset that are trees.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct Path {
+            pub cells: Vec<(usize, usize)>,
+            pub trees: Vec<(usize, usize)>,
+        }
+
+        // The map is parsed once into rows of bits (1 = tree). It repeats to the right forever,
+        // so columns wrap around the width.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct TobogganMap {
+            width: usize,
+            rows: Vec<Vec<u64>>,
+        }
+
+        impl TobogganMap {
+            pub fn parse(lines: &[String]) -> Result<TobogganMap, String> {
+                let width = lines.first().map_or(0, |l| l.chars().count());
+                let mut rows = vec![];
+                for (row, line) in lines.iter().enumerate() {
+                    if line.chars().count() != width {
+                        return Err(format!("Row {} is not {} cells wide.", row, width));
+                    }
+                    let mut bits = vec![0u64; width.div_ceil(64)];
+                    for (col, c) in line.chars().enumerate() {
+                        match c {
+                            '#' => bits[col / 64] |= 1 << (col % 64),
+                            '.' => (),
+                            _ => return Err(format!("Unexpected `{}` in row {}.", c, row)),
+                        }
+                    }
+                    rows.push(bits);
+                }
+                Ok(TobogganMap { width, rows })
+            }
+
+            pub fn from_file(filename: &str) -> TobogganMap {
+                TobogganMap::parse(&super::lines_from_file(filename)).expect("Could not parse map.")
+            }
+
+            pub fn width(&self) -> usize {
+                self.width
+            }
+
+            pub fn height(&self) -> usize {
+                self.rows.len()
+            }
+
+            pub fn is_tree(&self, row: usize, col: usize) -> bool {
+                let col = col % self.width;
+                self.rows[row][col / 64] & (1 << (col % 64)) != 0
+            }
+
+            pub fn trace(&self, slope: Slope) -> Path {
+                let mut path = Path {
+                    cells: vec![],
+                    trees: vec![],
+                };
+                if self.width == 0 || slope.down == 0 {
+                    return path;
+                }
+                let step = slope.right.rem_euclid(self.width as i64) as usize;
+                let mut col = 0;
+                for row in (slope.down..self.height()).step_by(slope.down) {
+                    col = (col + step) % self.width;
+                    path.cells.push((row, col));
+                    if self.is_tree(row, col) {
+                        path.trees.push((row, col));
+                    }
+                }
+                path
+            }
+
+            pub fn count_trees(&self, slope: Slope) -> usize {
+                self.trace(slope).trees.len()
+            }
+
+            // Draws the map with the path marked the way the puzzle does: `O` for open cells
+            // landed on and `X` for trees hit.
+            pub fn render_path(&self, slope: Slope) -> String {
+                let mut grid: Vec<Vec<char>> = (0..self.height())
+                    .map(|row| {
+                        (0..self.width)
+                            .map(|col| if self.is_tree(row, col) { '#' } else { '.' })
+                            .collect()
+                    })
+                    .collect();
+                for (row, col) in self.trace(slope).cells {
+                    grid[row][col] = if self.is_tree(row, col) { 'X' } else { 'O' };
+                }
+                grid.iter()
+                    .map(|row| row.iter().collect::<String>() + "\n")
+                    .collect()
+            }
+        }
+
+        // Answers questions over every slope with `right` in 1..=max_right and `down` in
+        // 1..=max_down. Moving right by `r` or by `r + width` lands on the same cells, so the tree
+        // counts are computed once per (down, right % width) from the map's rows and cached. A map
+        // with no columns has no slopes to search.
+        #[derive(Debug, Clone)]
+        pub struct SlopeSearch {
+            max_right: i64,
+            max_down: usize,
+            width: usize,
+            counts: Vec<Vec<usize>>,
+        }
+
+        impl SlopeSearch {
+            pub fn new(map: &TobogganMap, max_right: i64, max_down: usize) -> SlopeSearch {
+                let width = map.width();
+                let residues = (max_right.max(0) as usize + 1).min(width);
+                let mut counts = vec![vec![0; residues]; max_down];
+                for (d, row_counts) in counts.iter_mut().enumerate() {
+                    let down = d + 1;
+                    for (k, row) in (down..map.height()).step_by(down).enumerate() {
+                        let steps = k + 1;
+                        for (r, count) in row_counts.iter_mut().enumerate() {
+                            if map.is_tree(row, (steps * r) % width) {
+                                *count += 1;
+                            }
+                        }
+                    }
+                }
+                SlopeSearch {
+                    max_right,
+                    max_down,
+                    width,
+                    counts,
+                }
+            }
+
+            pub fn slopes(&self) -> impl Iterator<Item = Slope> + '_ {
+                let max_down = if self.width == 0 { 0 } else { self.max_down };
+                (1..=max_down).flat_map(move |down| {
+                    (1..=self.max_right).map(move |right| Slope { right, down })
+                })
+            }
+
+            // None when the slope is outside the search bounds.
+            pub fn count(&self, slope: Slope) -> Option<usize> {
+                if !(1..=self.max_down).contains(&slope.down)
+                    || !(1..=self.max_right).contains(&slope.right)
+                {
+                    return None;
+                }
+                if self.width == 0 {
+                    return Some(0);
+                }
+                Some(self.counts[slope.down - 1][(slope.right as usize) % self.width])
+            }
+
+            fn counted_slopes(&self) -> impl Iterator<Item = (Slope, usize)> + '_ {
+                self.slopes().filter_map(move |s| Some((s, self.count(s)?)))
+            }
+
+            // Ties go to the slope with the smallest `down`, then the smallest `right`.
+            pub fn fewest_trees(&self) -> Option<(Slope, usize)> {
+                self.counted_slopes().min_by_key(|&(_, c)| c)
+            }
+
+            pub fn most_trees(&self) -> Option<(Slope, usize)> {
+                self.counted_slopes().fold(
+                    None,
+                    |best: Option<(Slope, usize)>, (s, c)| match best {
+                        Some((_, best_count)) if best_count >= c => best,
+                        _ => Some((s, c)),
+                    },
+                )
+            }
+
+            // The tree counts are never negative, so the `n` slopes with the fewest trees give
+            // the smallest product.
+            pub fn min_product(&self, n: usize) -> Option<(Vec<Slope>, u128)> {
+                let mut ranked: Vec<(Slope, usize)> = self.counted_slopes().collect();
+                if ranked.len() < n {
+                    return None;
+                }
+                ranked.sort_by_key(|&(_, c)| c);
+                ranked.truncate(n);
+                let product = ranked.iter().fold(1u128, |acc, &(_, c)| {
+                    acc.checked_mul(c as u128)
+                        .expect("Product of tree counts overflowed u128.")
+                });
+                Some((ranked.into_iter().map(|(s, _)| s).collect(), product))
+            }
+        }
+
+        pub fn solve(problem: super::Problem, filename: &str) -> i32 {
+            let map = TobogganMap::from_file(filename);
+
+            match problem {
+                super::Problem::One => map.count_trees(Slope { right: 3, down: 1 }) as i32,
+                super::Problem::Two => PART_TWO_SLOPES
+                    .iter()
+                    .map(|&slope| map.count_trees(slope) as i32)
+                    .product(),
+            }
+        }
+    }
+
+    pub mod day_four {
+        use crate::aoc::{records, records_from_file, Record};
         use itertools::Itertools;
+        use std::fmt;
         use std::fs;
 
-        pub fn solve(problem: super::Problem, file: &str) -> i32 {
-            let subset_size = match problem {
-                super::Problem::One => 2,
-                super::Problem::Two => 3,
-            };
+        // The built-in rule sets, written in the same format `Schema::parse` reads.
+        pub const PART_ONE_SCHEMA: &str = "\
+byr required any
+iyr required any
+eyr required any
+hgt required any
+hcl required any
+ecl required any
+pid required any
+cid optional any
+";
+
+        pub const PART_TWO_SCHEMA: &str = "\
+byr required range 1920 2002
+iyr required range 2010 2020
+eyr required range 2020 2030
+hgt required units cm:150-193 in:59-76
+hcl required hex-color
+ecl required one-of amb blu brn gry grn hzl oth
+pid required digits 9
+cid optional any
+";
+
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub enum Validator {
+            Any,
+            IntRange(i64, i64),
+            UnitRange(Vec<(String, i64, i64)>),
+            HexColor,
+            OneOf(Vec<String>),
+            Digits(usize),
+        }
+
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub enum ValueError {
+            OutOfRange { allowed: String },
+            Malformed { expected: String },
+        }
+
+        impl Validator {
+            pub fn is_valid(&self, value: &str) -> bool {
+                self.check(value).is_ok()
+            }
+
+            pub fn check(&self, value: &str) -> Result<(), ValueError> {
+                let malformed = |expected: &str| {
+                    Err(ValueError::Malformed {
+                        expected: expected.to_string(),
+                    })
+                };
+                match self {
+                    Validator::Any => Ok(()),
+                    Validator::IntRange(min, max) => check_range(value, *min, *max, ""),
+                    Validator::UnitRange(units) => {
+                        // Several units can end the value (`m` and `cm`), so the value passes
+                        // if any of them accepts it. Otherwise the longest one explains why.
+                        let mut matching: Vec<&(String, i64, i64)> = units
+                            .iter()
+                            .filter(|(unit, _, _)| value.ends_with(unit.as_str()))
+                            .collect();
+                        matching.sort_by_key(|(unit, _, _)| std::cmp::Reverse(unit.len()));
+                        let mut first_error = None;
+                        for (unit, min, max) in matching {
+                            let number = &value[..value.len() - unit.len()];
+                            match check_range(number, *min, *max, unit) {
+                                Ok(()) => return Ok(()),
+                                Err(e) => {
+                                    first_error.get_or_insert(e);
+                                }
+                            }
+                        }
+                        match first_error {
+                            Some(e) => Err(e),
+                            None => {
+                                let unit_names: Vec<&str> =
+                                    units.iter().map(|u| u.0.as_str()).collect();
+                                malformed(&format!(
+                                    "a number followed by {}",
+                                    unit_names.join(" or ")
+                                ))
+                            }
+                        }
+                    }
+                    Validator::HexColor => match value.strip_prefix('#') {
+                        Some(hex)
+                            if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) =>
+                        {
+                            Ok(())
+                        }
+                        _ => malformed("# followed by 6 hex digits"),
+                    },
+                    Validator::OneOf(options) => {
+                        if options.iter().any(|o| o == value) {
+                            Ok(())
+                        } else {
+                            Err(ValueError::OutOfRange {
+                                allowed: format!("one of {}", options.join(", ")),
+                            })
+                        }
+                    }
+                    Validator::Digits(width) => {
+                        if value.len() == *width && value.chars().all(|c| c.is_ascii_digit()) {
+                            Ok(())
+                        } else {
+                            malformed(&format!("{} digits", width))
+                        }
+                    }
+                }
+            }
+        }
+
+        fn check_range(value: &str, min: i64, max: i64, unit: &str) -> Result<(), ValueError> {
+            if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+                return Err(ValueError::Malformed {
+                    expected: format!(
+                        "a number{}",
+                        if unit.is_empty() {
+                            "".to_string()
+                        } else {
+                            format!(" before {}", unit)
+                        }
+                    ),
+                });
+            }
+            match value.parse::<i64>() {
+                Ok(n) if (min..=max).contains(&n) => Ok(()),
+                _ => Err(ValueError::OutOfRange {
+                    allowed: format!("{}-{}{}", min, max, unit),
+                }),
+            }
+        }
+
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub enum DiagnosticKind {
+            MissingField(String),
+            DuplicateField(String),
+            UnknownField(String),
+            // A token without a `name:value` shape.
+            MalformedField(String),
+            OutOfRange {
+                field: String,
+                value: String,
+                allowed: String,
+            },
+            MalformedValue {
+                field: String,
+                value: String,
+                expected: String,
+            },
+        }
+
+        // `record` and `line` are both 1-based. A missing field is reported on the record's
+        // first line.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct Diagnostic {
+            pub record: usize,
+            pub line: usize,
+            pub kind: DiagnosticKind,
+        }
+
+        impl fmt::Display for Diagnostic {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "record {} (line {}): ", self.record, self.line)?;
+                match &self.kind {
+                    DiagnosticKind::MissingField(name) => write!(f, "missing field `{}`", name),
+                    DiagnosticKind::DuplicateField(name) => write!(f, "duplicate field `{}`", name),
+                    DiagnosticKind::UnknownField(name) => write!(f, "unknown field `{}`", name),
+                    DiagnosticKind::MalformedField(text) => {
+                        write!(f, "`{}` is not a `name:value` field", text)
+                    }
+                    DiagnosticKind::OutOfRange {
+                        field,
+                        value,
+                        allowed,
+                    } => write!(
+                        f,
+                        "{} `{}` is out of range, allowed {}",
+                        field, value, allowed
+                    ),
+                    DiagnosticKind::MalformedValue {
+                        field,
+                        value,
+                        expected,
+                    } => write!(
+                        f,
+                        "{} `{}` is malformed, expected {}",
+                        field, value, expected
+                    ),
+                }
+            }
+        }
+
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct FieldRule {
+            pub name: String,
+            pub required: bool,
+            pub validator: Validator,
+        }
+
+        // Each non-blank line of a schema file is `name required|optional validator args...`,
+        // where the validator is one of `any`, `range MIN MAX`, `units UNIT:MIN-MAX...`,
+        // `hex-color`, `one-of OPTION...` or `digits WIDTH`. `#` starts a comment.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct Schema {
+            pub fields: Vec<FieldRule>,
+        }
+
+        impl Schema {
+            pub fn part_one() -> Schema {
+                Schema::parse(PART_ONE_SCHEMA).expect("Built-in schema is valid.")
+            }
+
+            pub fn part_two() -> Schema {
+                Schema::parse(PART_TWO_SCHEMA).expect("Built-in schema is valid.")
+            }
+
+            pub fn from_file(filename: &str) -> Result<Schema, String> {
+                let config = fs::read_to_string(filename)
+                    .map_err(|e| format!("Could not read `{}`: {}", filename, e))?;
+                Schema::parse(&config)
+            }
+
+            pub fn parse(config: &str) -> Result<Schema, String> {
+                let mut fields: Vec<FieldRule> = vec![];
+                for (i, line) in config.lines().enumerate() {
+                    let words: Vec<&str> = line
+                        .split('#')
+                        .next()
+                        .unwrap_or("")
+                        .split_whitespace()
+                        .collect();
+                    if words.is_empty() {
+                        continue;
+                    }
+                    let error = |message: &str| format!("Schema line {}: {}", i + 1, message);
+                    if words.len() < 3 {
+                        return Err(error("expected `name required|optional validator`."));
+                    }
+                    let name = words[0].to_string();
+                    if fields.iter().any(|f| f.name == name) {
+                        return Err(error(&format!("field `{}` is defined twice.", name)));
+                    }
+                    let required = match words[1] {
+                        "required" => true,
+                        "optional" => false,
+                        other => {
+                            return Err(error(&format!("`{}` is not required/optional.", other)))
+                        }
+                    };
+                    let validator =
+                        parse_validator(words[2], &words[3..]).map_err(|e| error(&e))?;
+                    fields.push(FieldRule {
+                        name,
+                        required,
+                        validator,
+                    });
+                }
+                Ok(Schema { fields })
+            }
+
+            pub fn rule(&self, name: &str) -> Option<&FieldRule> {
+                self.fields.iter().find(|f| f.name == name)
+            }
+
+            // A record is valid when every required field is present and every field is known,
+            // appears once and passes its validator.
+            pub fn is_valid(&self, record: &str) -> bool {
+                let record = Record {
+                    number: 1,
+                    lines: vec![(1, record.to_string())],
+                };
+                self.diagnose(&record).is_empty()
+            }
+
+            // Lists every problem with one record.
+            pub fn diagnose(&self, record: &Record) -> Vec<Diagnostic> {
+                let fields: Vec<Field> = record
+                    .lines
+                    .iter()
+                    .flat_map(|(line, text)| {
+                        text.split_whitespace()
+                            .map(move |field| (*line, field.split_once(':').ok_or(field)))
+                    })
+                    .collect();
+                self.diagnose_fields(record.number, record.first_line(), &fields)
+            }
+
+            // Lists every problem with a passport read from any format. `number` is its
+            // position among the passports read.
+            pub fn diagnose_passport(&self, number: usize, passport: &Passport) -> Vec<Diagnostic> {
+                let fields: Vec<Field> = passport
+                    .fields
+                    .iter()
+                    .map(|(name, value)| (passport.line, Ok((name.as_str(), value.as_str()))))
+                    .collect();
+                self.diagnose_fields(number, passport.line, &fields)
+            }
+
+            fn diagnose_fields(
+                &self,
+                record: usize,
+                first_line: usize,
+                fields: &[Field],
+            ) -> Vec<Diagnostic> {
+                let mut problems = vec![];
+                let mut seen: Vec<&str> = vec![];
+                for &(line, field) in fields {
+                    let mut report = |kind| problems.push(Diagnostic { record, line, kind });
+                    let (name, value) = match field {
+                        Ok(pair) => pair,
+                        Err(token) => {
+                            report(DiagnosticKind::MalformedField(token.to_string()));
+                            continue;
+                        }
+                    };
+                    if seen.contains(&name) {
+                        report(DiagnosticKind::DuplicateField(name.to_string()));
+                        continue;
+                    }
+                    seen.push(name);
+                    let rule = match self.rule(name) {
+                        Some(rule) => rule,
+                        None => {
+                            report(DiagnosticKind::UnknownField(name.to_string()));
+                            continue;
+                        }
+                    };
+                    match rule.validator.check(value) {
+                        Ok(()) => (),
+                        Err(ValueError::OutOfRange { allowed }) => {
+                            report(DiagnosticKind::OutOfRange {
+                                field: name.to_string(),
+                                value: value.to_string(),
+                                allowed,
+                            })
+                        }
+                        Err(ValueError::Malformed { expected }) => {
+                            report(DiagnosticKind::MalformedValue {
+                                field: name.to_string(),
+                                value: value.to_string(),
+                                expected,
+                            })
+                        }
+                    }
+                }
+                for rule in &self.fields {
+                    if rule.required && !seen.contains(&rule.name.as_str()) {
+                        problems.push(Diagnostic {
+                            record,
+                            line: first_line,
+                            kind: DiagnosticKind::MissingField(rule.name.clone()),
+                        });
+                    }
+                }
+                problems
+            }
+        }
+
+        // A field's line and either its `name:value` split or the raw token.
+        type Field<'a> = (usize, Result<(&'a str, &'a str), &'a str>);
+
+        // Fields keep the order they were read in, duplicates included, so that validation
+        // sees exactly what the source held. `line` is the 1-based line the passport starts on.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct Passport {
+            pub line: usize,
+            pub fields: Vec<(String, String)>,
+        }
+
+        impl Passport {
+            pub fn get(&self, name: &str) -> Option<&str> {
+                self.fields
+                    .iter()
+                    .find(|f| f.0 == name)
+                    .map(|f| f.1.as_str())
+            }
+
+            pub fn from_record(record: &Record) -> Result<Passport, String> {
+                let mut fields = vec![];
+                for (line, text) in &record.lines {
+                    for field in text.split_whitespace() {
+                        let (name, value) = field.split_once(':').ok_or_else(|| {
+                            format!("line {}: `{}` is not a `name:value` field", line, field)
+                        })?;
+                        fields.push((name.to_string(), value.to_string()));
+                    }
+                }
+                Ok(Passport {
+                    line: record.first_line(),
+                    fields,
+                })
+            }
+
+            // Writes the passport as one `name:value name:value` line.
+            pub fn to_record(&self) -> Result<String, String> {
+                let mut fields = vec![];
+                for (name, value) in &self.fields {
+                    if name.is_empty() || name.contains(':') || name.contains(char::is_whitespace) {
+                        return Err(format!(
+                            "field name `{}` cannot be written as a record",
+                            name
+                        ));
+                    }
+                    if value.contains(char::is_whitespace) {
+                        return Err(format!(
+                            "{} `{}` cannot be written as a record",
+                            name, value
+                        ));
+                    }
+                    fields.push(format!("{}:{}", name, value));
+                }
+                Ok(fields.join(" "))
+            }
+
+            pub fn from_json_line(line: usize, text: &str) -> Result<Passport, String> {
+                let fields =
+                    super::json_object(text).map_err(|e| format!("line {}: {}", line, e))?;
+                Ok(Passport { line, fields })
+            }
+
+            pub fn to_json_line(&self) -> String {
+                let members: Vec<String> = self
+                    .fields
+                    .iter()
+                    .map(|(name, value)| {
+                        format!("{}:{}", super::json_string(name), super::json_string(value))
+                    })
+                    .collect();
+                format!("{{{}}}", members.join(","))
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub enum Format {
+            Records,
+            JsonLines,
+            Csv,
+        }
+
+        impl Format {
+            // Picks the format from the file extension, defaulting to blank-line records.
+            pub fn from_path(filename: &str) -> Format {
+                match filename.rsplit_once('.').map(|(_, ext)| ext) {
+                    Some("jsonl") => Format::JsonLines,
+                    Some("csv") => Format::Csv,
+                    _ => Format::Records,
+                }
+            }
+
+            pub fn parse(&self, contents: &str) -> Result<Vec<Passport>, String> {
+                self.parse_each(contents).into_iter().collect()
+            }
+
+            // Parses each passport on its own, so one bad record or line does not hide the
+            // rest. A CSV file that cannot be split into rows gives a single error.
+            pub fn parse_each(&self, contents: &str) -> Vec<Result<Passport, String>> {
+                match self {
+                    Format::Records => records(contents)
+                        .iter()
+                        .map(Passport::from_record)
+                        .collect(),
+                    Format::JsonLines => contents
+                        .lines()
+                        .enumerate()
+                        .filter(|(_, text)| !text.trim().is_empty())
+                        .map(|(i, text)| Passport::from_json_line(i + 1, text))
+                        .collect(),
+                    Format::Csv => passports_from_csv(contents),
+                }
+            }
+
+            pub fn write(&self, passports: &[Passport]) -> Result<String, String> {
+                match self {
+                    Format::Records => {
+                        let records: Result<Vec<String>, String> =
+                            passports.iter().map(|p| p.to_record()).collect();
+                        Ok(records?.iter().map(|r| format!("{}\n", r)).join("\n"))
+                    }
+                    Format::JsonLines => Ok(passports
+                        .iter()
+                        .map(|p| format!("{}\n", p.to_json_line()))
+                        .collect()),
+                    Format::Csv => passports_to_csv(passports),
+                }
+            }
+        }
+
+        // One column per field name, in order of first appearance. An empty cell means the
+        // passport has no such field, so empty values and repeated fields cannot be written.
+        fn passports_to_csv(passports: &[Passport]) -> Result<String, String> {
+            let mut columns: Vec<&str> = vec![];
+            for passport in passports {
+                for (name, value) in &passport.fields {
+                    if value.is_empty() {
+                        return Err(format!("{} has an empty value", name));
+                    }
+                    if passport.fields.iter().filter(|f| &f.0 == name).count() > 1 {
+                        return Err(format!("{} appears more than once", name));
+                    }
+                    if !columns.contains(&name.as_str()) {
+                        columns.push(name);
+                    }
+                }
+            }
+            let mut csv = columns.iter().map(|c| super::csv_field(c)).join(",");
+            csv.push('\n');
+            for passport in passports {
+                let row = columns
+                    .iter()
+                    .map(|c| super::csv_field(passport.get(c).unwrap_or("")))
+                    .join(",");
+                csv.push_str(&row);
+                csv.push('\n');
+            }
+            Ok(csv)
+        }
+
+        fn passports_from_csv(contents: &str) -> Vec<Result<Passport, String>> {
+            let mut rows = match super::csv_rows(contents) {
+                Ok(rows) => rows.into_iter(),
+                Err(e) => return vec![Err(e)],
+            };
+            let header = match rows.next() {
+                Some((_, header)) => header,
+                None => return vec![],
+            };
+            rows.map(|(line, row)| {
+                if row.len() != header.len() {
+                    return Err(format!(
+                        "line {}: expected {} cells, found {}",
+                        line,
+                        header.len(),
+                        row.len()
+                    ));
+                }
+                let fields = header
+                    .iter()
+                    .zip(row)
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(name, value)| (name.clone(), value))
+                    .collect();
+                Ok(Passport { line, fields })
+            })
+            .collect()
+        }
+
+        pub fn read_passports(filename: &str) -> Result<Vec<Passport>, String> {
+            let contents = fs::read_to_string(filename)
+                .map_err(|e| format!("could not read `{}`: {}", filename, e))?;
+            Format::from_path(filename).parse(&contents)
+        }
+
+        pub fn count_valid_passports(passports: &[Passport], schema: &Schema) -> i32 {
+            passports
+                .iter()
+                .enumerate()
+                .filter(|(i, p)| schema.diagnose_passport(i + 1, p).is_empty())
+                .count() as i32
+        }
+
+        pub fn diagnose_str(contents: &str, schema: &Schema) -> Vec<Diagnostic> {
+            records(contents)
+                .iter()
+                .flat_map(|record| schema.diagnose(record))
+                .collect()
+        }
 
-            let contents = fs::read_to_string(file).expect("Something went wrong reading the file");
+        pub fn diagnose(filename: &str, schema: &Schema) -> Vec<Diagnostic> {
+            records_from_file(filename)
+                .iter()
+                .flat_map(|record| schema.diagnose(record))
+                .collect()
+        }
 
-            let combos: Vec<Vec<i32>> = contents
-                .split_whitespace()
-                .map(|s| s.parse().expect("parse error"))
-                .dedup()
-                .combinations(subset_size)
-                .filter(|v: &Vec<i32>| v.iter().copied().sum::<i32>() == 2020)
-                .collect();
+        fn parse_validator(kind: &str, args: &[&str]) -> Result<Validator, String> {
+            let number = |n: &str| {
+                n.parse::<i64>()
+                    .map_err(|_| format!("`{}` is not a number.", n))
+            };
+            let expect_args = |count: usize| {
+                if args.len() == count {
+                    Ok(())
+                } else {
+                    Err(format!("`{}` takes {} argument(s).", kind, count))
+                }
+            };
+            match kind {
+                "any" => expect_args(0).map(|_| Validator::Any),
+                "hex-color" => expect_args(0).map(|_| Validator::HexColor),
+                "range" => {
+                    expect_args(2)?;
+                    Ok(Validator::IntRange(number(args[0])?, number(args[1])?))
+                }
+                "digits" => {
+                    expect_args(1)?;
+                    let width = args[0]
+                        .parse()
+                        .map_err(|_| format!("`{}` is not a width.", args[0]))?;
+                    Ok(Validator::Digits(width))
+                }
+                "one-of" if !args.is_empty() => Ok(Validator::OneOf(
+                    args.iter().map(|a| a.to_string()).collect(),
+                )),
+                "units" if !args.is_empty() => {
+                    let mut units = vec![];
+                    for arg in args {
+                        let parsed = arg.split_once(':').and_then(|(unit, range)| {
+                            let (min, max) = range.split_once('-')?;
+                            Some((unit, min, max))
+                        });
+                        let (unit, min, max) =
+                            parsed.ok_or_else(|| format!("`{}` is not UNIT:MIN-MAX.", arg))?;
+                        units.push((unit.to_string(), number(min)?, number(max)?));
+                    }
+                    Ok(Validator::UnitRange(units))
+                }
+                "one-of" | "units" => Err(format!("`{}` needs at least one argument.", kind)),
+                _ => Err(format!("unknown validator `{}`.", kind)),
+            }
+        }
+
+        // A passport that cannot be parsed counts as invalid in every format, just as a
+        // malformed field fails its own blank-line record.
+        pub fn count_valid(filename: &str, schema: &Schema) -> i32 {
+            match Format::from_path(filename) {
+                Format::Records => records_from_file(filename)
+                    .iter()
+                    .filter(|record| schema.diagnose(record).is_empty())
+                    .count() as i32,
+                format => {
+                    let contents = fs::read_to_string(filename).expect("Could not read file.");
+                    format
+                        .parse_each(&contents)
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, passport)| {
+                            passport
+                                .as_ref()
+                                .is_ok_and(|p| schema.diagnose_passport(i + 1, p).is_empty())
+                        })
+                        .count() as i32
+                }
+            }
+        }
 
-            combos[0].iter().product()
+        pub fn solve(problem: super::Problem, filename: &str) -> i32 {
+            let schema = match problem {
+                super::Problem::One => Schema::part_one(),
+                super::Problem::Two => Schema::part_two(),
+            };
+            count_valid(filename, &schema)
         }
     }
 
-    pub mod day_two {
-        use std::fs::File;
-        use std::io::BufRead;
-        use std::io::BufReader;
+    pub mod day_five {
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub struct Seat {
+            pub row: u64,
+            pub column: u64,
+            pub id: u64,
+        }
 
-        #[derive(Debug, Clone)]
-        struct PasswordInfo {
-            range: (i32, i32),
-            letter: char,
-            password: String,
+        // Encodes the row in `F`/`B` and then the column in `L`/`R`, one character per bit,
+        // most significant first.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub struct BoardingPass {
+            row_bits: u32,
+            column_bits: u32,
         }
 
-        impl PasswordInfo {
-            fn new(line: &str) -> Self {
-                let fields: Vec<&str> = line.split_whitespace().collect();
-                let range = PasswordInfo::parse_range(fields[0]);
-                let letter = fields[1]
-                    .chars()
-                    .next()
-                    .expect("Letter could not be parsed.");
-                let password = fields[2].to_string();
-                PasswordInfo {
-                    range,
-                    letter,
-                    password,
+        impl BoardingPass {
+            pub fn new(row_bits: u32, column_bits: u32) -> Result<BoardingPass, String> {
+                match row_bits.checked_add(column_bits) {
+                    Some(1..=63) => {}
+                    Some(bits) => {
+                        return Err(format!(
+                            "a boarding pass needs between 1 and 63 bits, not {}",
+                            bits
+                        ))
+                    }
+                    None => return Err("a boarding pass needs between 1 and 63 bits".to_string()),
                 }
+                Ok(BoardingPass {
+                    row_bits,
+                    column_bits,
+                })
             }
 
-            fn parse_range(range: &str) -> (i32, i32) {
-                let min_to_max: Vec<&str> = range.split('-').collect();
-                let min = min_to_max[0].parse().expect("Error parsing range minimum.");
-                let max = min_to_max[1].parse().expect("Error parsing range maximum.");
-                (min, max)
+            pub fn standard() -> BoardingPass {
+                BoardingPass {
+                    row_bits: 7,
+                    column_bits: 3,
+                }
             }
 
-            fn d02_p01_valid(&self) -> bool {
-                let letter_count = self.password.matches(self.letter).count() as i32;
-                letter_count >= self.range.0 && letter_count <= self.range.1
+            pub fn row_bits(&self) -> u32 {
+                self.row_bits
             }
 
-            fn d02_p02_valid(&self) -> bool {
-                let first_index = self.range.0 - 1;
-                let second_index = self.range.1 - 1;
-                let first_letter = self.password.chars().nth(first_index as usize).expect("");
-                let second_letter = self.password.chars().nth(second_index as usize).expect("");
-                first_letter == self.letter && second_letter != self.letter
-                    || first_letter != self.letter && second_letter == self.letter
+            pub fn column_bits(&self) -> u32 {
+                self.column_bits
             }
-        }
-
-        fn helper(filename: &str, validator: fn(p: &PasswordInfo) -> bool) -> i32 {
-            let file = File::open(filename).expect("Could not open file.");
-            let reader = BufReader::new(file);
 
-            reader
-                .lines()
-                .map(|x| PasswordInfo::new(&x.expect("")))
-                .filter(|x| validator(&x))
-                .count() as i32
-        }
-
-        pub fn solve(problem: super::Problem, file: &str) -> i32 {
-            match problem {
-                super::Problem::One => helper(file, PasswordInfo::d02_p01_valid),
-                super::Problem::Two => helper(file, PasswordInfo::d02_p02_valid),
+            pub fn rows(&self) -> u64 {
+                1 << self.row_bits
             }
-        }
-    }
 
-    pub mod day_three {
+            pub fn columns(&self) -> u64 {
+                1 << self.column_bits
+            }
 
-        struct Move {
-            down: usize,
-            right: usize,
-        }
+            pub fn seat_id(&self, row: u64, column: u64) -> u64 {
+                (row << self.column_bits) | column
+            }
 
-        pub fn solve(problem: super::Problem, filename: &str) -> i32 {
-            let terrain = super::lines_from_file(filename);
+            pub fn seat(&self, id: u64) -> Option<Seat> {
+                if id >= self.rows() * self.columns() {
+                    return None;
+                }
+                Some(Seat {
+                    row: id >> self.column_bits,
+                    column: id & (self.columns() - 1),
+                    id,
+                })
+            }
 
-            match problem {
-                super::Problem::One => tree_counter(&terrain, &Move { down: 1, right: 3 }),
-                super::Problem::Two => {
-                    let moves: Vec<Move> = vec![
-                        Move { down: 1, right: 1 },
-                        Move { down: 1, right: 3 },
-                        Move { down: 1, right: 5 },
-                        Move { down: 1, right: 7 },
-                        Move { down: 2, right: 1 },
-                    ];
-                    moves.iter().map(|m| tree_counter(&terrain, m)).product()
+            pub fn decode(&self, code: &str) -> Result<Seat, String> {
+                let width = (self.row_bits + self.column_bits) as usize;
+                if code.chars().count() != width {
+                    return Err(format!(
+                        "`{}` has {} characters, expected {}",
+                        code,
+                        code.chars().count(),
+                        width
+                    ));
+                }
+                let mut row = 0;
+                let mut column = 0;
+                for (i, c) in code.chars().enumerate() {
+                    let (value, bit) = if i < self.row_bits as usize {
+                        (&mut row, ['F', 'B'].iter().position(|&x| x == c))
+                    } else {
+                        (&mut column, ['L', 'R'].iter().position(|&x| x == c))
+                    };
+                    let bit = bit.ok_or_else(|| {
+                        let expected = if i < self.row_bits as usize {
+                            "F or B"
+                        } else {
+                            "L or R"
+                        };
+                        format!(
+                            "`{}` has `{}` at position {}, expected {}",
+                            code,
+                            c,
+                            i + 1,
+                            expected
+                        )
+                    })?;
+                    *value = (*value << 1) | bit as u64;
                 }
+                Ok(Seat {
+                    row,
+                    column,
+                    id: self.seat_id(row, column),
+                })
             }
-        }
 
-        fn tree_counter(lines: &[String], movement: &Move) -> i32 {
-            let mut tree_count = 0;
-            let mut char_index = 0;
-            for (line_count, line) in lines.iter().enumerate() {
-                if line_count > 0 && line_count % movement.down == 0 {
-                    char_index += movement.right;
-                    char_index %= line.chars().count();
+            pub fn encode(&self, row: u64, column: u64) -> Result<String, String> {
+                if row >= self.rows() {
+                    return Err(format!(
+                        "row {} is past the last row {}",
+                        row,
+                        self.rows() - 1
+                    ));
                 }
-                if line.chars().nth(char_index) == Some('#')
-                    && line_count > 0
-                    && line_count % movement.down == 0
-                {
-                    tree_count += 1;
+                if column >= self.columns() {
+                    return Err(format!(
+                        "column {} is past the last column {}",
+                        column,
+                        self.columns() - 1
+                    ));
                 }
+                let bits = |value: u64, width: u32, letters: [char; 2]| {
+                    (0..width)
+                        .rev()
+                        .map(|b| letters[(value >> b & 1) as usize])
+                        .collect::<String>()
+                };
+                Ok(bits(row, self.row_bits, ['F', 'B'])
+                    + &bits(column, self.column_bits, ['L', 'R']))
             }
-            tree_count
         }
-    }
 
-    pub mod day_four {
-        use std::fs;
-        use std::fs::File;
-        use std::io::Read;
+        // The widest plane a seat map will lay out, one flag per seat.
+        pub const MAX_SEAT_MAP_BITS: u32 = 24;
 
-        fn valid(entry: &str, problem: super::Problem) -> bool {
-            match problem {
-                super::Problem::One => {
-                    let field_count = entry.split_whitespace().count();
-                    field_count == 8 || (field_count == 7 && !entry.contains("cid"))
+        // Which seats of a plane are taken, indexed by seat id.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct SeatMap {
+            codec: BoardingPass,
+            taken: Vec<bool>,
+        }
+
+        impl SeatMap {
+            pub fn new(codec: BoardingPass, codes: &[String]) -> Result<SeatMap, String> {
+                let bits = codec.row_bits() + codec.column_bits();
+                if bits > MAX_SEAT_MAP_BITS {
+                    return Err(format!(
+                        "a seat map holds at most {} bits of seats, not {}",
+                        MAX_SEAT_MAP_BITS, bits
+                    ));
                 }
-                super::Problem::Two => {
-                    if !valid(entry, super::Problem::One) {
-                        return false;
-                    }
-                    for field in entry.split_whitespace() {
-                        let title = field.split(':').collect::<Vec<&str>>()[0];
-                        let value = field.split(':').collect::<Vec<&str>>()[1];
-                        match title {
-                            "byr" => {
-                                if !(1920..=2002).contains(
-                                    &value.parse::<i32>().expect("Could not parse 'byr' value"),
-                                ) {
-                                    return false;
-                                }
-                            }
-                            "iyr" => {
-                                if !(2010..=2020).contains(
-                                    &value.parse::<i32>().expect("Could not parse 'iyr' value"),
-                                ) {
-                                    return false;
-                                }
-                            }
-                            "eyr" => {
-                                if !(2020..=2030).contains(
-                                    &value.parse::<i32>().expect("Could not parse 'eyr' value"),
-                                ) {
-                                    return false;
-                                }
-                            }
-                            "hgt" => {
-                                if value.contains("cm") {
-                                    if !(150..=193).contains(
-                                        &value
-                                            .trim_end_matches("cm")
-                                            .parse::<i32>()
-                                            .expect("Could not parse 'hgt cm' value"),
-                                    ) {
-                                        return false;
-                                    }
-                                } else if value.contains("in") {
-                                    if !(59..=76).contains(
-                                        &value
-                                            .trim_end_matches("in")
-                                            .parse::<i32>()
-                                            .expect("Could not parse 'hgt in' value"),
-                                    ) {
-                                        return false;
-                                    }
-                                } else {
-                                    return false;
-                                }
-                            }
-                            "hcl" => {
-                                if value.starts_with('#') && value.len() == 7 {
-                                    if !(0..0xffffff).contains(
-                                        &i64::from_str_radix(&value[1..], 16)
-                                            .expect("Could not parse 'hcl #' in value"),
-                                    ) {
-                                        return false;
-                                    }
-                                } else {
-                                    return false;
-                                }
-                            }
-                            "ecl" => {
-                                let eye_colors =
-                                    vec!["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-                                if eye_colors
-                                    .iter()
-                                    .map(|c| c == &value)
-                                    .filter(|x| *x)
-                                    .count()
-                                    != 1
-                                {
-                                    return false;
-                                }
-                            }
-                            "pid" => {
-                                if value.len() != 9
-                                    || !(0..=999999999).contains(
-                                        &value.parse::<i32>().expect("Could not parse 'pid' value"),
-                                    )
-                                {
-                                    return false;
-                                }
-                            }
-                            "cid" => {}
-                            _ => panic!("Title of entry does not match known titles"),
-                        };
-                    }
-                    true
+                let mut taken = vec![false; (codec.rows() * codec.columns()) as usize];
+                for code in codes {
+                    taken[codec.decode(code)?.id as usize] = true;
                 }
+                Ok(SeatMap { codec, taken })
             }
-        }
 
-        pub fn solve(problem: super::Problem, filename: &str) -> i32 {
-            let mut s = String::new();
-            let mut file = File::open(filename).expect("Could not open file.");
-            fs::File::read_to_string(&mut file, &mut s).expect("Could not parse file.");
+            pub fn from_file(filename: &str, codec: BoardingPass) -> Result<SeatMap, String> {
+                SeatMap::new(codec, &super::lines_from_file(filename))
+            }
 
-            s.split("\n\n")
-                .map(|e| valid(e, problem))
-                .filter(|x| *x)
-                .count() as i32
+            pub fn is_taken(&self, id: u64) -> bool {
+                self.taken.get(id as usize).is_some_and(|&t| t)
+            }
+
+            pub fn taken(&self) -> impl Iterator<Item = Seat> + '_ {
+                (0..self.taken.len() as u64)
+                    .filter(move |&id| self.is_taken(id))
+                    .filter_map(move |id| self.codec.seat(id))
+            }
+
+            pub fn highest(&self) -> Option<Seat> {
+                self.taken().last()
+            }
+
+            // Empty seats whose ids on both sides are taken.
+            pub fn gaps(&self) -> Vec<Seat> {
+                (1..self.taken.len().saturating_sub(1) as u64)
+                    .filter(|&id| {
+                        !self.is_taken(id) && self.is_taken(id - 1) && self.is_taken(id + 1)
+                    })
+                    .filter_map(|id| self.codec.seat(id))
+                    .collect()
+            }
+
+            // One line per row, `#` for a taken seat and `.` for a free one.
+            pub fn render(&self) -> String {
+                self.taken
+                    .chunks(self.codec.columns() as usize)
+                    .map(|row| {
+                        let mut line: String =
+                            row.iter().map(|&t| if t { '#' } else { '.' }).collect();
+                        line.push('\n');
+                        line
+                    })
+                    .collect()
+            }
+        }
+
+        pub fn solve(problem: super::Problem, filename: &str) -> Option<i32> {
+            let seats = SeatMap::from_file(filename, BoardingPass::standard())
+                .expect("Could not parse boarding pass.");
+            let seat = match problem {
+                super::Problem::One => seats.highest(),
+                super::Problem::Two => seats.gaps().first().copied(),
+            };
+            seat.map(|s| s.id as i32)
         }
     }
 
-    pub mod day_five {
-        fn boarding_zone_to_seat_id(zone: &str) -> i32 {
-            let row = &zone[..7].replace('F', "0").replace('B', "1");
-            let col = &zone[7..].replace('L', "0").replace('R', "1");
-            let row_num = i64::from_str_radix(row, 2).expect("Could not parse row");
-            let col_num = i64::from_str_radix(col, 2).expect("Could not parse col");
-            (row_num * 8 + col_num) as i32
+    pub mod day_six {
+        use crate::aoc::{records_from_file, Record};
+
+        pub const QUESTIONS: usize = 26;
+
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub enum Quantifier {
+            Anyone,
+            Everyone,
+            Nobody,
+            AtLeast(usize),
+            Exactly(usize),
         }
 
-        pub fn solve(problem: super::Problem, filename: &str) -> i32 {
-            let boarding_zones = super::lines_from_file(filename);
-            let seat_ids: Vec<_> = boarding_zones
-                .iter()
-                .map(|x| boarding_zone_to_seat_id(x))
-                .collect();
-            match problem {
-                super::Problem::One => *seat_ids.iter().max().unwrap() as i32,
-                super::Problem::Two => {
-                    for seat in &seat_ids {
-                        if !seat_ids.contains(&(seat + 1)) && seat_ids.contains(&(seat + 2)) {
-                            return (*seat + 1) as i32;
+        // One 26-bit mask per member, bit 0 for question `a`.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct Group {
+            pub line: usize,
+            pub members: Vec<u32>,
+        }
+
+        impl Group {
+            pub fn from_record(record: &Record) -> Result<Group, String> {
+                let mut members = vec![];
+                for (line, answers) in &record.lines {
+                    let mut mask = 0;
+                    for c in answers.chars() {
+                        if !c.is_ascii_lowercase() {
+                            return Err(format!("line {}: `{}` is not a question", line, c));
                         }
+                        mask |= 1 << (c as u8 - b'a');
                     }
-                    -42
+                    members.push(mask);
                 }
+                Ok(Group {
+                    line: record.first_line(),
+                    members,
+                })
             }
-        }
-    }
 
-    pub mod day_six {
-        use itertools::Itertools;
-        use std::fs;
-        use std::fs::File;
-        use std::io::Read;
+            // How many members answered each question.
+            pub fn histogram(&self) -> [usize; QUESTIONS] {
+                let mut counts = [0; QUESTIONS];
+                for mask in &self.members {
+                    for (question, count) in counts.iter_mut().enumerate() {
+                        *count += (mask >> question & 1) as usize;
+                    }
+                }
+                counts
+            }
 
-        pub fn solve(problem: super::Problem, filename: &str) -> i32 {
-            let mut s = String::new();
-            let mut file = File::open(filename).expect("Could not open file.");
-            fs::File::read_to_string(&mut file, &mut s).expect("Could not parse file.");
+            pub fn questions(&self, quantifier: Quantifier) -> u32 {
+                let histogram = self.histogram();
+                let matches = |count: usize| match quantifier {
+                    Quantifier::Anyone => count > 0,
+                    Quantifier::Everyone => count == self.members.len(),
+                    Quantifier::Nobody => count == 0,
+                    Quantifier::AtLeast(k) => count >= k,
+                    Quantifier::Exactly(k) => count == k,
+                };
+                (0..QUESTIONS)
+                    .filter(|&q| matches(histogram[q]))
+                    .fold(0, |mask, q| mask | 1 << q)
+            }
 
-            let groups = s.split("\n\n");
-            match problem {
-                super::Problem::One => groups.map(|x| count_anyone(x)).sum::<i32>() as i32,
-                super::Problem::Two => groups.map(|x| count_everyone(x)).sum::<i32>() as i32,
+            pub fn count(&self, quantifier: Quantifier) -> u32 {
+                self.questions(quantifier).count_ones()
             }
         }
 
-        fn count_anyone(group: &str) -> i32 {
-            group
-                .split_whitespace()
-                .collect::<String>()
-                .chars()
-                .unique()
-                .count() as i32
+        pub fn letters(mask: u32) -> String {
+            (0..QUESTIONS as u8)
+                .filter(|q| mask >> q & 1 == 1)
+                .map(|q| (b'a' + q) as char)
+                .collect()
         }
 
-        fn count_everyone(group: &str) -> i32 {
-            let num_members_in_group = group.matches('\n').count() + 1;
-            let letters_in_group: Vec<_> = group
-                .split_whitespace()
-                .collect::<String>()
-                .chars()
-                .unique()
-                .collect();
-            letters_in_group
+        pub fn histogram(groups: &[Group]) -> [usize; QUESTIONS] {
+            let mut counts = [0; QUESTIONS];
+            for group in groups {
+                for (total, count) in counts.iter_mut().zip(group.histogram()) {
+                    *total += count;
+                }
+            }
+            counts
+        }
+
+        pub fn groups_from_file(filename: &str) -> Result<Vec<Group>, String> {
+            records_from_file(filename)
                 .iter()
-                .map(|&x| group.matches(x).count())
-                .filter(|&x| x == num_members_in_group)
-                .count() as i32
+                .map(Group::from_record)
+                .collect()
+        }
+
+        pub fn count(groups: &[Group], quantifier: Quantifier) -> u32 {
+            groups.iter().map(|g| g.count(quantifier)).sum()
+        }
+
+        pub fn solve(problem: super::Problem, filename: &str) -> i32 {
+            let groups = groups_from_file(filename).expect("Could not parse answers.");
+            let quantifier = match problem {
+                super::Problem::One => Quantifier::Anyone,
+                super::Problem::Two => Quantifier::Everyone,
+            };
+            count(&groups, quantifier) as i32
         }
     }
 
@@ -398,7 +2382,7 @@ pub mod aoc {
         ) {
             let accu_copy = accu.clone();
             for bag in accu_copy.iter() {
-                find_bag(&map, bag, accu);
+                find_bag(map, bag, accu);
             }
 
             if accu.len() > accu_copy.len() {
@@ -431,7 +2415,7 @@ pub mod aoc {
                     Some(
                         contents
                             .split(", ")
-                            .map(|s| parse_single_bag(s))
+                            .map(parse_single_bag)
                             .collect::<Vec<(usize, String)>>(),
                     )
                 } else {
@@ -458,24 +2442,197 @@ pub mod aoc {
 
     pub mod day_eight {
         use crate::aoc::lines_from_file;
+        use std::collections::HashMap;
+        use std::fmt;
 
         #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-        enum OpCode {
+        pub enum OpCode {
             Nop,
             Acc,
             Jmp,
         }
 
-        #[derive(Debug, Copy, Clone)]
-        struct Instruction {
-            op: OpCode,
-            val: i32,
+        impl fmt::Display for OpCode {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let mnemonic = match self {
+                    OpCode::Nop => "nop",
+                    OpCode::Acc => "acc",
+                    OpCode::Jmp => "jmp",
+                };
+                write!(f, "{}", mnemonic)
+            }
+        }
+
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        pub struct Instruction {
+            pub op: OpCode,
+            pub val: i32,
+        }
+
+        impl fmt::Display for Instruction {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{} {:+}", self.op, self.val)
+            }
+        }
+
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub enum ProgramExit {
+            Terminates,
+            OutOfBounds { line: usize, target: i64 },
+            InfiniteLoop(Vec<usize>),
+        }
+
+        // Every instruction has exactly one successor, so the graph is just a target per line.
+        // Targets are kept as `i64` since a `jmp` is allowed to point outside the program, even
+        // past the range of `i32`.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct ControlFlowGraph {
+            targets: Vec<i64>,
+            predecessors: Vec<Vec<usize>>,
+        }
+
+        impl ControlFlowGraph {
+            pub fn new(instructions: &[Instruction]) -> ControlFlowGraph {
+                let end = instructions.len();
+                let targets: Vec<i64> = instructions
+                    .iter()
+                    .enumerate()
+                    .map(|(i, instr)| next_line(instr, i as i64))
+                    .collect();
+                let mut predecessors: Vec<Vec<usize>> = vec![vec![]; end + 1];
+                for (i, &target) in targets.iter().enumerate() {
+                    if (0..=end as i64).contains(&target) {
+                        predecessors[target as usize].push(i);
+                    }
+                }
+                ControlFlowGraph {
+                    targets,
+                    predecessors,
+                }
+            }
+
+            pub fn target(&self, line: usize) -> i64 {
+                self.targets[line]
+            }
+
+            pub fn predecessors(&self, line: usize) -> &[usize] {
+                &self.predecessors[line]
+            }
+
+            fn in_program(&self, line: i64) -> bool {
+                (0..self.targets.len() as i64).contains(&line)
+            }
+
+            pub fn reachable(&self) -> Vec<bool> {
+                let mut reachable = vec![false; self.targets.len()];
+                let mut line: i64 = 0;
+                while self.in_program(line) && !reachable[line as usize] {
+                    reachable[line as usize] = true;
+                    line = self.targets[line as usize];
+                }
+                reachable
+            }
+
+            pub fn unreachable(&self) -> Vec<usize> {
+                self.reachable()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &r)| !r)
+                    .map(|(i, _)| i)
+                    .collect()
+            }
+
+            // Reverse reachability from the address just past the last instruction: a line is
+            // marked if running the program from it terminates normally.
+            pub fn reaches_end(&self) -> Vec<bool> {
+                let end = self.targets.len();
+                let mut reaches_end = vec![false; end + 1];
+                reaches_end[end] = true;
+                let mut stack = vec![end];
+                while let Some(line) = stack.pop() {
+                    for &prev in &self.predecessors[line] {
+                        if !reaches_end[prev] {
+                            reaches_end[prev] = true;
+                            stack.push(prev);
+                        }
+                    }
+                }
+                reaches_end
+            }
+
+            // Each cycle is listed once, starting from its lowest line number.
+            pub fn loops(&self) -> Vec<Vec<usize>> {
+                let len = self.targets.len();
+                // 0 = unseen, 1 = on the current walk, 2 = finished
+                let mut state = vec![0u8; len];
+                let mut loops = vec![];
+                for start in 0..len {
+                    let mut walk = vec![];
+                    let mut line = start as i64;
+                    while self.in_program(line) && state[line as usize] == 0 {
+                        state[line as usize] = 1;
+                        walk.push(line as usize);
+                        line = self.targets[line as usize];
+                    }
+                    if self.in_program(line) && state[line as usize] == 1 {
+                        let cycle_start = walk
+                            .iter()
+                            .position(|&l| l == line as usize)
+                            .expect("Cycle start is on the current walk.");
+                        let mut cycle = walk[cycle_start..].to_vec();
+                        let min_pos = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap_or(0);
+                        cycle.rotate_left(min_pos);
+                        loops.push(cycle);
+                    }
+                    for l in walk {
+                        state[l] = 2;
+                    }
+                }
+                loops.sort();
+                loops
+            }
+
+            pub fn exit(&self) -> ProgramExit {
+                let end = self.targets.len() as i64;
+                let mut seen = vec![false; self.targets.len()];
+                let mut line: i64 = 0;
+                let mut previous: Option<usize> = None;
+                loop {
+                    if line == end {
+                        return ProgramExit::Terminates;
+                    }
+                    if !self.in_program(line) {
+                        return ProgramExit::OutOfBounds {
+                            line: previous.unwrap_or(0),
+                            target: line,
+                        };
+                    }
+                    if seen[line as usize] {
+                        let entry = line as usize;
+                        return ProgramExit::InfiniteLoop(
+                            self.loops()
+                                .into_iter()
+                                .find(|cycle| cycle.contains(&entry))
+                                .expect("Revisited line lies on a cycle."),
+                        );
+                    }
+                    seen[line as usize] = true;
+                    previous = Some(line as usize);
+                    line = self.targets[line as usize];
+                }
+            }
         }
 
         #[derive(Debug, Copy, Clone)]
         struct AccumulatorInfo {
             value: i32,
-            end_index: i32,
+            end_index: i64,
+        }
+
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        pub struct Repair {
+            pub index: usize,
+            pub accumulator: i32,
         }
 
         pub fn solve(problem: super::Problem, filename: &str) -> Option<i32> {
@@ -485,26 +2642,37 @@ pub mod aoc {
 
             match problem {
                 super::Problem::One => Some(problem_one(&instructions).value),
-                super::Problem::Two => problem_two(&instructions),
+                super::Problem::Two => Some(problem_two(&instructions)?.accumulator),
+            }
+        }
+
+        pub fn repair(filename: &str) -> Option<Repair> {
+            let ops = lines_from_file(filename);
+            let instructions: Vec<Instruction> =
+                ops.iter().map(|op| parse_instruction(op)).collect();
+            problem_two(&instructions)
+        }
+
+        fn next_line(instr: &Instruction, line_num: i64) -> i64 {
+            match instr.op {
+                OpCode::Jmp => line_num + instr.val as i64,
+                _ => line_num + 1,
             }
         }
 
         fn problem_one(instructions: &[Instruction]) -> AccumulatorInfo {
             let mut global_accumulator = 0;
-            let mut visited: Vec<i32> = vec![];
-            let mut line_num: i32 = 0;
+            let mut visited = vec![false; instructions.len()];
+            let mut line_num: i64 = 0;
 
-            while (!visited.contains(&line_num)) && (line_num < instructions.len() as i32) {
-                visited.push(line_num);
+            while (0..instructions.len() as i64).contains(&line_num) && !visited[line_num as usize]
+            {
+                visited[line_num as usize] = true;
                 let instr = instructions[line_num as usize];
-                match instr.op {
-                    OpCode::Acc => {
-                        global_accumulator += instr.val;
-                        line_num += 1;
-                    }
-                    OpCode::Jmp => line_num += instr.val,
-                    _ => line_num += 1,
+                if instr.op == OpCode::Acc {
+                    global_accumulator += instr.val;
                 }
+                line_num = next_line(&instr, line_num);
             }
 
             AccumulatorInfo {
@@ -513,36 +2681,53 @@ pub mod aoc {
             }
         }
 
-        fn problem_two(instructions: &[Instruction]) -> Option<i32> {
-            let mut index = 0;
-            loop {
-                if index == instructions.len() {
-                    return None;
-                }
-                match instructions[index].op {
-                    OpCode::Jmp | OpCode::Nop => {
-                        let mut temp = instructions.to_owned();
-                        swap_jmp_and_nop(&mut temp, index);
-                        let accumulator = problem_one(&temp);
-                        if accumulator.end_index == temp.len() as i32 {
-                            return Some(accumulator.value);
+        fn problem_two(instructions: &[Instruction]) -> Option<Repair> {
+            let end = instructions.len() as i64;
+            let reaches_end = ControlFlowGraph::new(instructions).reaches_end();
+            let leads_to_end =
+                |line_num: i64| (0..=end).contains(&line_num) && reaches_end[line_num as usize];
+
+            // Walk the original (looping) path once. The corrupted instruction must be on it,
+            // and flipping it has to land on a line that already runs through to the end.
+            let mut visited = vec![false; instructions.len()];
+            let mut line_num: i64 = 0;
+            while (0..end).contains(&line_num) && !visited[line_num as usize] {
+                let index = line_num as usize;
+                visited[index] = true;
+                let instr = instructions[index];
+                if let Some(flipped) = swap_jmp_and_nop(instr) {
+                    if leads_to_end(next_line(&flipped, line_num)) {
+                        let mut repaired = instructions.to_owned();
+                        repaired[index] = flipped;
+                        let accumulator = problem_one(&repaired);
+                        if accumulator.end_index == end {
+                            return Some(Repair {
+                                index,
+                                accumulator: accumulator.value,
+                            });
                         }
-                        index += 1;
                     }
-                    _ => index += 1,
                 }
+                line_num = next_line(&instr, line_num);
             }
+            None
         }
 
-        fn swap_jmp_and_nop(instructions: &mut [Instruction], index: usize) {
-            match instructions[index].op {
-                OpCode::Jmp => instructions[index].op = OpCode::Nop,
-                OpCode::Nop => instructions[index].op = OpCode::Jmp,
-                _ => (),
+        fn swap_jmp_and_nop(instr: Instruction) -> Option<Instruction> {
+            match instr.op {
+                OpCode::Jmp => Some(Instruction {
+                    op: OpCode::Nop,
+                    val: instr.val,
+                }),
+                OpCode::Nop => Some(Instruction {
+                    op: OpCode::Jmp,
+                    val: instr.val,
+                }),
+                OpCode::Acc => None,
             }
         }
 
-        fn parse_instruction(instr: &str) -> Instruction {
+        pub fn parse_instruction(instr: &str) -> Instruction {
             let instruction_parts: Vec<&str> = instr.split(' ').collect();
             let op = match instruction_parts[0] {
                 "acc" => OpCode::Acc,
@@ -554,18 +2739,171 @@ pub mod aoc {
                 .expect("Could not parse instruction value.");
             Instruction { op, val }
         }
+
+        // Accepts one instruction per line, with optional `name:` labels, `#` comments and
+        // blank lines. A `jmp` or `nop` may use a label in place of its offset.
+        pub fn assemble(source: &str) -> Result<Vec<Instruction>, String> {
+            let mut labels: HashMap<&str, i32> = HashMap::new();
+            let mut statements: Vec<(usize, &str)> = vec![];
+            for (line_num, line) in source.lines().enumerate() {
+                let mut code = line.split('#').next().unwrap_or("").trim();
+                while let Some(colon) = code.find(':') {
+                    let label = code[..colon].trim();
+                    if !is_label(label) {
+                        return Err(format!("Line {}: invalid label `{}`.", line_num + 1, label));
+                    }
+                    if labels.insert(label, statements.len() as i32).is_some() {
+                        return Err(format!(
+                            "Line {}: duplicate label `{}`.",
+                            line_num + 1,
+                            label
+                        ));
+                    }
+                    code = code[colon + 1..].trim();
+                }
+                if !code.is_empty() {
+                    statements.push((line_num + 1, code));
+                }
+            }
+
+            let mut instructions = vec![];
+            for (address, (line_num, code)) in statements.into_iter().enumerate() {
+                let parts: Vec<&str> = code.split_whitespace().collect();
+                if parts.len() != 2 {
+                    return Err(format!("Line {}: expected `op argument`.", line_num));
+                }
+                let (op, arg) = (parts[0], parts[1]);
+                if !["nop", "acc", "jmp"].contains(&op) {
+                    return Err(format!("Line {}: unknown operation `{}`.", line_num, op));
+                }
+                let val = if let Ok(val) = arg.parse::<i32>() {
+                    val
+                } else if op == "acc" {
+                    return Err(format!("Line {}: `acc` needs a number.", line_num));
+                } else {
+                    match labels.get(arg) {
+                        Some(target) => target - address as i32,
+                        None => return Err(format!("Line {}: unknown label `{}`.", line_num, arg)),
+                    }
+                };
+                instructions.push(parse_instruction(&format!("{} {:+}", op, val)));
+            }
+            Ok(instructions)
+        }
+
+        fn is_label(label: &str) -> bool {
+            !label.is_empty()
+                && !label.starts_with(|c: char| c.is_ascii_digit())
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+
+        // The output can be fed back into `assemble`; the line numbers and jump targets are
+        // written as comments.
+        pub fn disassemble(instructions: &[Instruction]) -> String {
+            let cfg = ControlFlowGraph::new(instructions);
+            let reachable = cfg.reachable();
+            let mut output = String::new();
+            for (i, instr) in instructions.iter().enumerate() {
+                let mut comment = format!("{}", i);
+                if instr.op == OpCode::Jmp {
+                    let target = cfg.target(i);
+                    if target == instructions.len() as i64 {
+                        comment.push_str(&format!(" -> {} (end)", target));
+                    } else if cfg.in_program(target) {
+                        comment.push_str(&format!(" -> {}", target));
+                    } else {
+                        comment.push_str(&format!(" -> {} (out of bounds)", target));
+                    }
+                }
+                if !reachable[i] {
+                    comment.push_str(" (unreachable)");
+                }
+                output.push_str(&format!("{:<11} # {}\n", instr.to_string(), comment));
+            }
+            output
+        }
     }
 
     pub mod day_nine {
         use crate::aoc::lines_from_file;
         use itertools::Itertools;
+        use std::collections::{HashMap, VecDeque};
 
-        #[derive(Debug, Copy, Clone)]
-        struct InvalidNumberInfo {
-            number: i64,
+        const PREAMBLE: usize = 25;
+
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        pub struct InvalidNumberInfo {
+            pub number: i64,
+            pub index: usize,
+        }
+
+        // Keeps the last `preamble` numbers along with a count of every pairwise sum between
+        // them, so each new number is checked in constant time and the sums are updated in
+        // time linear in the preamble. Pairs whose sum overflows `i64` can never match a number,
+        // so they are left out of the counts.
+        #[derive(Debug, Clone)]
+        pub struct XmasValidator {
+            preamble: usize,
+            window: VecDeque<i64>,
+            sums: HashMap<i64, usize>,
             index: usize,
         }
 
+        impl XmasValidator {
+            pub fn new(preamble: usize) -> XmasValidator {
+                XmasValidator {
+                    preamble,
+                    window: VecDeque::with_capacity(preamble),
+                    sums: HashMap::new(),
+                    index: 0,
+                }
+            }
+
+            // Returns the number's info if it is not a sum of two numbers in the window.
+            pub fn push(&mut self, number: i64) -> Option<InvalidNumberInfo> {
+                let mut invalid = None;
+                if self.window.len() == self.preamble {
+                    if !self.sums.contains_key(&number) {
+                        invalid = Some(InvalidNumberInfo {
+                            number,
+                            index: self.index,
+                        });
+                    }
+                    if let Some(oldest) = self.window.pop_front() {
+                        for sum in self.window.iter().filter_map(|o| oldest.checked_add(*o)) {
+                            let count = self
+                                .sums
+                                .get_mut(&sum)
+                                .expect("Pair sum is missing from the window.");
+                            *count -= 1;
+                            if *count == 0 {
+                                self.sums.remove(&sum);
+                            }
+                        }
+                    }
+                }
+                if self.preamble > 0 {
+                    for sum in self.window.iter().filter_map(|o| number.checked_add(*o)) {
+                        *self.sums.entry(sum).or_insert(0) += 1;
+                    }
+                    self.window.push_back(number);
+                }
+                self.index += 1;
+                invalid
+            }
+        }
+
+        pub fn invalid_numbers<I>(
+            numbers: I,
+            preamble: usize,
+        ) -> impl Iterator<Item = InvalidNumberInfo>
+        where
+            I: IntoIterator<Item = i64>,
+        {
+            let mut validator = XmasValidator::new(preamble);
+            numbers.into_iter().filter_map(move |n| validator.push(n))
+        }
+
         pub fn solve(problem: super::Problem, filename: &str) -> Option<i64> {
             let numbers: Vec<i64> = lines_from_file(filename)
                 .iter()
@@ -573,62 +2911,94 @@ pub mod aoc {
                 .collect();
 
             match problem {
-                super::Problem::One => Some(problem_one(&numbers)?.number),
+                super::Problem::One => Some(problem_one(&numbers, PREAMBLE)?.number),
                 super::Problem::Two => problem_two(&numbers),
             }
         }
 
-        fn problem_one(numbers: &[i64]) -> Option<InvalidNumberInfo> {
-            let window_size = 25;
-            for i in window_size..numbers.len() {
-                if !is_number_valid(numbers, i, window_size) {
-                    return Some(InvalidNumberInfo {
-                        number: numbers[i],
-                        index: i,
-                    });
-                }
-            }
-            None
+        fn problem_one(numbers: &[i64], preamble: usize) -> Option<InvalidNumberInfo> {
+            invalid_numbers(numbers.iter().copied(), preamble).next()
         }
 
-        fn is_number_valid(numbers: &[i64], index: usize, window_size: usize) -> bool {
-            let start = index - window_size;
-            let perms = numbers[start..index].iter().copied().permutations(2);
-            perms
-                .map(|p| p.iter().sum::<i64>())
-                .any(|x| x == numbers[index])
+        // `end` is exclusive, so the range covers `numbers[start..end]`.
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        pub struct ContiguousRange {
+            pub start: usize,
+            pub end: usize,
+            pub min: i64,
+            pub max: i64,
         }
 
         fn problem_two(numbers: &[i64]) -> Option<i64> {
-            let info = problem_one(&numbers)?;
+            let info = problem_one(numbers, PREAMBLE)?;
+            let range = find_contiguous_range(&numbers[..info.index], info.number)?;
+            Some(range.min + range.max)
+        }
 
-            let mut start_index = 0;
-            let mut end_index = 2;
-            loop {
-                let sum: i64 = numbers[start_index..end_index].iter().sum();
-                if (sum < info.number) && (end_index < info.index) {
-                    end_index += 1;
-                } else if (sum > info.number) || (end_index > info.index) {
-                    start_index += 1;
-                    end_index = start_index + 2;
-                } else if sum == info.number {
-                    let minmax = numbers[start_index..end_index]
-                        .iter()
-                        .minmax()
-                        .into_option()?;
-                    return Some(minmax.0 + minmax.1);
-                } else {
-                    return None;
+        // Finds a run of at least two numbers adding up to `target`, returning the one that
+        // ends earliest. Sums are kept in `i128`, so no run of `i64`s can overflow them.
+        pub fn find_contiguous_range(numbers: &[i64], target: i64) -> Option<ContiguousRange> {
+            let (start, end) = if numbers.iter().all(|&n| n >= 0) {
+                sliding_window(numbers, target)?
+            } else {
+                prefix_sums(numbers, target)?
+            };
+            let (min, max) = numbers[start..end].iter().copied().minmax().into_option()?;
+            Some(ContiguousRange {
+                start,
+                end,
+                min,
+                max,
+            })
+        }
+
+        // Only valid without negative numbers: growing the window can then never shrink the
+        // sum, so `start` only ever has to move forward.
+        fn sliding_window(numbers: &[i64], target: i64) -> Option<(usize, usize)> {
+            let target = target as i128;
+            let mut start = 0;
+            let mut sum = 0;
+            for (end, &number) in numbers.iter().enumerate() {
+                sum += number as i128;
+                while sum > target && start < end {
+                    sum -= numbers[start] as i128;
+                    start += 1;
+                }
+                if sum == target && end > start {
+                    return Some((start, end + 1));
+                }
+            }
+            None
+        }
+
+        fn prefix_sums(numbers: &[i64], target: i64) -> Option<(usize, usize)> {
+            let target = target as i128;
+            let mut first_seen: HashMap<i128, usize> = HashMap::new();
+            let mut prefix = vec![0; numbers.len() + 1];
+            for (i, &number) in numbers.iter().enumerate() {
+                prefix[i + 1] = prefix[i] + number as i128;
+            }
+            for end in 2..prefix.len() {
+                // Only allow starts that leave at least two numbers in the range.
+                first_seen.entry(prefix[end - 2]).or_insert(end - 2);
+                if let Some(&start) = first_seen.get(&(prefix[end] - target)) {
+                    return Some((start, end));
                 }
             }
+            None
         }
     }
 
     pub mod day_ten {
         use crate::aoc::lines_from_file;
         use itertools::Itertools;
+        use std::convert::TryFrom;
 
-        pub fn solve(problem: super::Problem, filename: &str) -> Option<i64> {
+        const MAX_JOLT_GAP: i32 = 3;
+
+        // Sorted adapter ratings with the outlet (0) in front and the device (highest adapter
+        // plus three) at the end.
+        pub fn joltage_ratings(filename: &str) -> Vec<i32> {
             let mut joltage_ratings: Vec<i32> = lines_from_file(filename)
                 .iter()
                 .map(|x| x.parse().expect("Could not parse integer."))
@@ -636,60 +3006,319 @@ pub mod aoc {
                 .collect();
 
             joltage_ratings.insert(0, 0);
-            joltage_ratings.push(joltage_ratings.last().expect("Empty vector.") + 3);
+            joltage_ratings.push(joltage_ratings.last().expect("Empty vector.") + MAX_JOLT_GAP);
+            joltage_ratings
+        }
 
-            let mut differences: Vec<i32> = vec![];
-            for i in 1..joltage_ratings.len() {
-                differences.push(joltage_ratings[i] - joltage_ratings[i - 1]);
-            }
+        pub fn solve(problem: super::Problem, filename: &str) -> Option<i64> {
+            let joltage_ratings = joltage_ratings(filename);
 
             match problem {
-                super::Problem::One => problem_one(&differences),
-                super::Problem::Two => problem_two(&differences),
+                super::Problem::One => problem_one(&joltage_ratings),
+                super::Problem::Two => {
+                    i64::try_from(count_arrangements(&joltage_ratings, MAX_JOLT_GAP)?).ok()
+                }
             }
         }
 
-        fn problem_one(differences: &[i32]) -> Option<i64> {
+        fn problem_one(joltage_ratings: &[i32]) -> Option<i64> {
+            let mut differences: Vec<i32> = vec![];
+            for i in 1..joltage_ratings.len() {
+                differences.push(joltage_ratings[i] - joltage_ratings[i - 1]);
+            }
+
             Some(
                 differences.iter().filter(|x| **x == 1).count() as i64
                     * differences.iter().filter(|x| **x == 3).count() as i64,
             )
         }
 
-        fn problem_two(differences: &[i32]) -> Option<i64> {
-            Some(
-                differences
-                    .split(|num| *num == 3)
-                    .filter(|n| !n.is_empty())
-                    .map(|x| calculate_combinations(x.len() as i64))
-                    .product(),
-            )
+        fn can_connect(joltage_ratings: &[i32], from: usize, to: usize, max_gap: i32) -> bool {
+            (1..=max_gap).contains(&(joltage_ratings[to] - joltage_ratings[from]))
         }
 
-        fn calculate_combinations(num: i64) -> i64 {
-            let total = num - 1;
-            let mut num_choices = total;
-            let mut num_combinations = 0;
-            loop {
-                if (total - num_choices > 2) || (num_choices < 0) {
-                    break;
-                } else if num_choices == 0 {
-                    num_combinations += 1;
-                    break;
+        // Counts the chains from the first rating to the last. `ways[i]` is the number of
+        // chains from the outlet that end at adapter `i`; since the ratings are sorted, only
+        // the adapters just below `i` can feed into it. None if the count overflows `u128`.
+        pub fn count_arrangements(joltage_ratings: &[i32], max_gap: i32) -> Option<u128> {
+            if joltage_ratings.is_empty() {
+                return Some(0);
+            }
+            let mut ways: Vec<u128> = vec![0; joltage_ratings.len()];
+            ways[0] = 1;
+            for to in 1..joltage_ratings.len() {
+                for from in (0..to).rev() {
+                    if joltage_ratings[to] - joltage_ratings[from] > max_gap {
+                        break;
+                    }
+                    if can_connect(joltage_ratings, from, to, max_gap) {
+                        ways[to] = ways[to].checked_add(ways[from])?;
+                    }
+                }
+            }
+            Some(ways[joltage_ratings.len() - 1])
+        }
+
+        // Adapters (excluding the outlet and the device) that appear in every valid chain.
+        // An adapter is mandatory when it is on some chain and no usable connection jumps
+        // over it.
+        pub fn mandatory_adapters(joltage_ratings: &[i32], max_gap: i32) -> Vec<i32> {
+            let len = joltage_ratings.len();
+            if len < 3 {
+                return vec![];
+            }
+
+            let mut from_outlet = vec![false; len];
+            from_outlet[0] = true;
+            for to in 1..len {
+                from_outlet[to] = (0..to)
+                    .rev()
+                    .take_while(|&from| joltage_ratings[to] - joltage_ratings[from] <= max_gap)
+                    .any(|from| {
+                        from_outlet[from] && can_connect(joltage_ratings, from, to, max_gap)
+                    });
+            }
+            let to_device = reaches_device(joltage_ratings, max_gap);
+            if !to_device[0] {
+                return vec![];
+            }
+
+            let usable: Vec<bool> = (0..len).map(|i| from_outlet[i] && to_device[i]).collect();
+            let mut mandatory = vec![];
+            // The furthest usable adapter reachable in one step from any usable adapter seen so
+            // far.
+            let mut furthest_jump = 0;
+            for i in 0..len - 1 {
+                if !usable[i] {
+                    continue;
+                }
+                if i > 0 && furthest_jump <= i {
+                    mandatory.push(joltage_ratings[i]);
+                }
+                if let Some(to) = next_adapters(joltage_ratings, i, max_gap)
+                    .filter(|&to| usable[to])
+                    .last()
+                {
+                    furthest_jump = furthest_jump.max(to);
+                }
+            }
+            mandatory
+        }
+
+        // Indices of the adapters that can be plugged directly into adapter `from`, in
+        // ascending order.
+        fn next_adapters(
+            joltage_ratings: &[i32],
+            from: usize,
+            max_gap: i32,
+        ) -> impl Iterator<Item = usize> + '_ {
+            (from + 1..joltage_ratings.len())
+                .take_while(move |&to| joltage_ratings[to] - joltage_ratings[from] <= max_gap)
+                .filter(move |&to| can_connect(joltage_ratings, from, to, max_gap))
+        }
+
+        fn reaches_device(joltage_ratings: &[i32], max_gap: i32) -> Vec<bool> {
+            let len = joltage_ratings.len();
+            let mut to_device = vec![false; len];
+            if len == 0 {
+                return to_device;
+            }
+            to_device[len - 1] = true;
+            for from in (0..len - 1).rev() {
+                to_device[from] =
+                    next_adapters(joltage_ratings, from, max_gap).any(|to| to_device[to]);
+            }
+            to_device
+        }
+
+        // Number of chains from each adapter up to the device, or None if any count overflows
+        // `u128`.
+        fn ways_to_device(joltage_ratings: &[i32], max_gap: i32) -> Option<Vec<u128>> {
+            let len = joltage_ratings.len();
+            let mut ways: Vec<u128> = vec![0; len];
+            if len == 0 {
+                return Some(ways);
+            }
+            ways[len - 1] = 1;
+            for from in (0..len - 1).rev() {
+                ways[from] = next_adapters(joltage_ratings, from, max_gap)
+                    .try_fold(0u128, |acc, to| acc.checked_add(ways[to]))?;
+            }
+            Some(ways)
+        }
+
+        fn to_ratings(joltage_ratings: &[i32], chain: &[usize]) -> Vec<i32> {
+            chain.iter().map(|&i| joltage_ratings[i]).collect()
+        }
+
+        // Yields every valid chain, outlet to device, in lexicographic order. Dead ends are
+        // pruned up front, so each chain costs time linear in its length. Use `take` to limit
+        // the number of chains.
+        #[derive(Debug, Clone)]
+        pub struct Chains<'a> {
+            joltage_ratings: &'a [i32],
+            max_gap: i32,
+            to_device: Vec<bool>,
+            path: Vec<usize>,
+            started: bool,
+        }
+
+        pub fn chains(joltage_ratings: &[i32], max_gap: i32) -> Chains<'_> {
+            Chains {
+                joltage_ratings,
+                max_gap,
+                to_device: reaches_device(joltage_ratings, max_gap),
+                path: vec![],
+                started: false,
+            }
+        }
+
+        impl<'a> Chains<'a> {
+            fn first_choice_after(&self, from: usize, after: Option<usize>) -> Option<usize> {
+                next_adapters(self.joltage_ratings, from, self.max_gap)
+                    .filter(|&to| after.is_none_or(|a| to > a))
+                    .find(|&to| self.to_device[to])
+            }
+
+            fn extend_to_device(&mut self) {
+                let end = self.joltage_ratings.len() - 1;
+                while let Some(&last) = self.path.last() {
+                    if last == end {
+                        break;
+                    }
+                    let next = self
+                        .first_choice_after(last, None)
+                        .expect("Adapter on a pruned path has no way to the device.");
+                    self.path.push(next);
+                }
+            }
+        }
+
+        impl<'a> Iterator for Chains<'a> {
+            type Item = Vec<i32>;
+
+            fn next(&mut self) -> Option<Vec<i32>> {
+                if !self.started {
+                    self.started = true;
+                    if self.to_device.first() != Some(&true) {
+                        return None;
+                    }
+                    self.path.push(0);
                 } else {
-                    num_combinations += count_combinations(total, num_choices);
-                    num_choices -= 1;
+                    // Backtrack to the deepest adapter that still has an untried successor.
+                    loop {
+                        let last = self.path.pop()?;
+                        let from = *self.path.last()?;
+                        if let Some(next) = self.first_choice_after(from, Some(last)) {
+                            self.path.push(next);
+                            break;
+                        }
+                    }
                 }
+                self.extend_to_device();
+                Some(to_ratings(self.joltage_ratings, &self.path))
             }
-            num_combinations
         }
 
-        fn count_combinations(n: i64, r: i64) -> i64 {
-            if r > n {
-                0
-            } else {
-                (1..=r).fold(1, |acc, val| acc * (n - val + 1) / val)
+        // SplitMix64, so sampling is reproducible from a seed without pulling in a crate.
+        #[derive(Debug, Clone)]
+        struct SplitMix64 {
+            state: u64,
+        }
+
+        impl SplitMix64 {
+            fn next_u64(&mut self) -> u64 {
+                self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = self.state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
             }
+
+            // Uniform in `0..bound`, using rejection so no value is favoured.
+            fn below(&mut self, bound: u128) -> u128 {
+                let zone = u128::MAX - u128::MAX % bound;
+                loop {
+                    let value = (u128::from(self.next_u64()) << 64) | u128::from(self.next_u64());
+                    if value < zone {
+                        return value % bound;
+                    }
+                }
+            }
+        }
+
+        // An endless stream of chains drawn uniformly at random: each step picks the next
+        // adapter with probability proportional to the number of chains that go through it.
+        // None if there are too many chains to count in `u128`.
+        pub fn sample_chains(
+            joltage_ratings: &[i32],
+            max_gap: i32,
+            seed: u64,
+        ) -> Option<impl Iterator<Item = Vec<i32>> + '_> {
+            let ways = ways_to_device(joltage_ratings, max_gap)?;
+            let mut rng = SplitMix64 { state: seed };
+            let has_chain = ways.first().is_some_and(|&w| w > 0);
+            let samples = std::iter::repeat(())
+                .take_while(move |_| has_chain)
+                .map(move |_| {
+                    let end = joltage_ratings.len() - 1;
+                    let mut chain = vec![0];
+                    let mut current = 0;
+                    while current != end {
+                        let mut pick = rng.below(ways[current]);
+                        for to in next_adapters(joltage_ratings, current, max_gap) {
+                            if pick < ways[to] {
+                                current = to;
+                                break;
+                            }
+                            pick -= ways[to];
+                        }
+                        chain.push(current);
+                    }
+                    to_ratings(joltage_ratings, &chain)
+                });
+            Some(samples)
+        }
+
+        pub fn shortest_chain(joltage_ratings: &[i32], max_gap: i32) -> Option<Vec<i32>> {
+            extreme_chain(joltage_ratings, max_gap, |candidate, best| candidate < best)
+        }
+
+        pub fn longest_chain(joltage_ratings: &[i32], max_gap: i32) -> Option<Vec<i32>> {
+            extreme_chain(joltage_ratings, max_gap, |candidate, best| candidate > best)
+        }
+
+        // `length[i]` is the best number of adapters from `i` to the device. Ties go to the
+        // lowest next adapter, so the result is the lexicographically smallest such chain.
+        fn extreme_chain(
+            joltage_ratings: &[i32],
+            max_gap: i32,
+            better: fn(usize, usize) -> bool,
+        ) -> Option<Vec<i32>> {
+            let len = joltage_ratings.len();
+            if len == 0 {
+                return None;
+            }
+            let mut length: Vec<Option<usize>> = vec![None; len];
+            let mut next: Vec<Option<usize>> = vec![None; len];
+            length[len - 1] = Some(1);
+            for from in (0..len - 1).rev() {
+                for to in next_adapters(joltage_ratings, from, max_gap) {
+                    if let Some(candidate) = length[to].map(|l| l + 1) {
+                        if length[from].is_none_or(|best| better(candidate, best)) {
+                            length[from] = Some(candidate);
+                            next[from] = Some(to);
+                        }
+                    }
+                }
+            }
+            length[0]?;
+
+            let mut chain = vec![0];
+            while let Some(to) = next[*chain.last()?] {
+                chain.push(to);
+            }
+            Some(to_ratings(joltage_ratings, &chain))
         }
     }
 
@@ -744,17 +3373,13 @@ pub mod aoc {
                     for j in 0..temp.num_cols {
                         let num_occupied = temp.count_occupied_adjacent_seats(i, j);
                         match temp.layout[i][j] {
-                            Some(Seat::Empty) => {
-                                if num_occupied == 0 {
-                                    self.layout[i][j] = Some(Seat::Occupied);
-                                }
+                            Some(Seat::Empty) if num_occupied == 0 => {
+                                self.layout[i][j] = Some(Seat::Occupied);
                             }
-                            Some(Seat::Occupied) => {
-                                if num_occupied >= 4 {
-                                    self.layout[i][j] = Some(Seat::Empty);
-                                }
+                            Some(Seat::Occupied) if num_occupied >= 4 => {
+                                self.layout[i][j] = Some(Seat::Empty);
                             }
-                            None => (),
+                            _ => (),
                         };
                     }
                 }
@@ -766,17 +3391,13 @@ pub mod aoc {
                     for j in 0..temp.num_cols {
                         let num_occupied = temp.count_visible_occupied_seats(i, j);
                         match temp.layout[i][j] {
-                            Some(Seat::Empty) => {
-                                if num_occupied == 0 {
-                                    self.layout[i][j] = Some(Seat::Occupied);
-                                }
+                            Some(Seat::Empty) if num_occupied == 0 => {
+                                self.layout[i][j] = Some(Seat::Occupied);
                             }
-                            Some(Seat::Occupied) => {
-                                if num_occupied >= 5 {
-                                    self.layout[i][j] = Some(Seat::Empty);
-                                }
+                            Some(Seat::Occupied) if num_occupied >= 5 => {
+                                self.layout[i][j] = Some(Seat::Empty);
                             }
-                            None => (),
+                            _ => (),
                         };
                     }
                 }
@@ -971,7 +3592,7 @@ pub mod aoc {
     }
 
     pub mod day_twelve {
-        use std::mem::swap;
+        use std::fmt;
 
         #[derive(Debug, Copy, Clone, Eq, PartialEq)]
         pub enum Direction {
@@ -986,12 +3607,12 @@ pub mod aoc {
 
         #[derive(Debug, Clone, Eq, PartialEq)]
         pub struct Instruction {
-            direction: Direction,
-            value: isize,
+            pub direction: Direction,
+            pub value: isize,
         }
 
         impl Instruction {
-            fn new(instruction: &str) -> Instruction {
+            pub fn new(instruction: &str) -> Instruction {
                 let dir = instruction.chars().next().expect("No direction to parse.");
                 let value: isize = instruction
                     .get(1..)
@@ -1017,174 +3638,416 @@ pub mod aoc {
             }
         }
 
+        impl fmt::Display for Instruction {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let letter = match self.direction {
+                    Direction::North => 'N',
+                    Direction::South => 'S',
+                    Direction::East => 'E',
+                    Direction::West => 'W',
+                    Direction::Left => 'L',
+                    Direction::Right => 'R',
+                    Direction::Forward => 'F',
+                };
+                write!(f, "{}{}", letter, self.value)
+            }
+        }
+
         #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-        pub enum Facing {
-            North,
-            South,
-            East,
-            West,
+        pub enum StepError {
+            UnsupportedAngle,
+            Overflow,
         }
 
-        #[derive(Debug, Clone, Eq, PartialEq)]
-        pub struct Location {
-            x: isize,
-            y: isize,
+        // Arithmetic returns None on overflow. Left turns are positive (anticlockwise) and right
+        // turns negative.
+        pub trait Coordinate: Copy + PartialOrd + fmt::Debug + fmt::Display {
+            fn from_int(value: isize) -> Self;
+            fn to_f64(self) -> f64;
+            fn add(self, other: Self) -> Option<Self>;
+            fn saturating_add(self, other: Self) -> Self;
+            fn scale(self, factor: isize) -> Option<Self>;
+            fn abs(self) -> Option<Self>;
+            fn rotate(vector: Vector<Self>, degrees: isize) -> Result<Vector<Self>, StepError>;
         }
 
-        trait State {
-            fn initialize() -> Self
-            where
-                Self: Sized;
-            fn update(&mut self, instruction: Instruction);
-            fn manhattan_distance(&self) -> isize;
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct Vector<T> {
+            pub x: T,
+            pub y: T,
         }
 
-        #[derive(Debug, Clone, Eq, PartialEq)]
-        pub struct P1State {
-            facing: Facing,
-            position: Location,
-        }
-
-        impl P1State {
-            fn turn_left(&mut self) {
-                match self.facing {
-                    Facing::North => self.facing = Facing::West,
-                    Facing::West => self.facing = Facing::South,
-                    Facing::South => self.facing = Facing::East,
-                    Facing::East => self.facing = Facing::North,
-                };
+        impl<T: Coordinate> Vector<T> {
+            pub fn new(x: isize, y: isize) -> Vector<T> {
+                Vector {
+                    x: T::from_int(x),
+                    y: T::from_int(y),
+                }
             }
 
-            fn turn_right(&mut self) {
-                match self.facing {
-                    Facing::North => self.facing = Facing::East,
-                    Facing::East => self.facing = Facing::South,
-                    Facing::South => self.facing = Facing::West,
-                    Facing::West => self.facing = Facing::North,
-                };
+            fn add(self, other: Vector<T>) -> Option<Vector<T>> {
+                Some(Vector {
+                    x: self.x.add(other.x)?,
+                    y: self.y.add(other.y)?,
+                })
+            }
+
+            fn saturating_add(self, other: Vector<T>) -> Vector<T> {
+                Vector {
+                    x: self.x.saturating_add(other.x),
+                    y: self.y.saturating_add(other.y),
+                }
+            }
+
+            fn scale(self, factor: isize) -> Option<Vector<T>> {
+                Some(Vector {
+                    x: self.x.scale(factor)?,
+                    y: self.y.scale(factor)?,
+                })
+            }
+
+            pub fn manhattan_distance(&self) -> Option<T> {
+                self.x.abs()?.add(self.y.abs()?)
+            }
+        }
+
+        // Rotates by a whole number of quarter turns, which is exact in any coordinate type.
+        fn quarter_turns<T: Coordinate>(
+            vector: Vector<T>,
+            degrees: isize,
+        ) -> Result<Vector<T>, StepError> {
+            if degrees % 90 != 0 {
+                return Err(StepError::UnsupportedAngle);
+            }
+            let Vector { x, y } = vector;
+            let minus = |v: T| v.scale(-1).ok_or(StepError::Overflow);
+            Ok(match degrees.rem_euclid(360) / 90 {
+                0 => vector,
+                1 => Vector { x: minus(y)?, y: x },
+                2 => Vector {
+                    x: minus(x)?,
+                    y: minus(y)?,
+                },
+                _ => Vector { x: y, y: minus(x)? },
+            })
+        }
+
+        impl Coordinate for isize {
+            fn from_int(value: isize) -> isize {
+                value
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn add(self, other: isize) -> Option<isize> {
+                self.checked_add(other)
+            }
+
+            fn saturating_add(self, other: isize) -> isize {
+                isize::saturating_add(self, other)
+            }
+
+            fn scale(self, factor: isize) -> Option<isize> {
+                self.checked_mul(factor)
+            }
+
+            fn abs(self) -> Option<isize> {
+                self.checked_abs()
             }
 
-            fn move_forward(&mut self, value: isize) {
-                match self.facing {
-                    Facing::North => self.position.y += value,
-                    Facing::South => self.position.y -= value,
-                    Facing::East => self.position.x += value,
-                    Facing::West => self.position.x -= value,
+            fn rotate(vector: Vector<isize>, degrees: isize) -> Result<Vector<isize>, StepError> {
+                quarter_turns(vector, degrees)
+            }
+        }
+
+        // Floating-point results overflow when they stop being finite.
+        impl Coordinate for f64 {
+            fn from_int(value: isize) -> f64 {
+                value as f64
+            }
+
+            fn to_f64(self) -> f64 {
+                self
+            }
+
+            fn add(self, other: f64) -> Option<f64> {
+                Some(self + other).filter(|v| v.is_finite())
+            }
+
+            fn saturating_add(self, other: f64) -> f64 {
+                (self + other).clamp(f64::MIN, f64::MAX)
+            }
+
+            fn scale(self, factor: isize) -> Option<f64> {
+                Some(self * factor as f64).filter(|v| v.is_finite())
+            }
+
+            fn abs(self) -> Option<f64> {
+                Some(f64::abs(self))
+            }
+
+            fn rotate(vector: Vector<f64>, degrees: isize) -> Result<Vector<f64>, StepError> {
+                if degrees % 90 == 0 {
+                    return quarter_turns(vector, degrees);
+                }
+                let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+                let rotated = Vector {
+                    x: vector.x * cos - vector.y * sin,
+                    y: vector.x * sin + vector.y * cos,
                 };
+                if rotated.x.is_finite() && rotated.y.is_finite() {
+                    Ok(rotated)
+                } else {
+                    Err(StepError::Overflow)
+                }
             }
         }
 
-        impl State for P1State {
-            fn initialize() -> P1State {
-                P1State {
-                    facing: Facing::East,
-                    position: Location { x: 0, y: 0 },
+        // With `Heading` the compass moves push the ship and `vector` is its unit heading;
+        // with `Waypoint` they push the waypoint, which `vector` holds relative to the ship.
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        pub enum Steering {
+            Heading,
+            Waypoint,
+        }
+
+        // `isize` coordinates turn by right angles only. `f64` coordinates turn by any angle
+        // unless `strict` holds them to right angles too.
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Navigator<T> {
+            pub steering: Steering,
+            pub strict: bool,
+            pub position: Vector<T>,
+            pub vector: Vector<T>,
+        }
+
+        impl<T: Coordinate> Navigator<T> {
+            pub fn new(steering: Steering) -> Navigator<T> {
+                let vector = match steering {
+                    Steering::Heading => Vector::new(1, 0),
+                    Steering::Waypoint => Vector::new(10, 1),
+                };
+                Navigator {
+                    steering,
+                    strict: false,
+                    position: Vector::new(0, 0),
+                    vector,
                 }
             }
 
-            fn update(&mut self, instruction: Instruction) {
-                match instruction.direction {
-                    Direction::Left => {
-                        let num_turns = instruction.value / 90;
-                        for _i in 0..num_turns {
-                            self.turn_left();
-                        }
+            // Every instruction is a single multiply-and-add or rotation, whatever its value.
+            // On error the navigator is left as it was.
+            pub fn update(&mut self, instruction: &Instruction) -> Result<(), String> {
+                self.step(instruction).map_err(|e| match e {
+                    StepError::UnsupportedAngle => {
+                        format!("`{}` is not a right-angle turn", instruction)
                     }
-                    Direction::Right => {
-                        let num_turns = instruction.value / 90;
-                        for _i in 0..num_turns {
-                            self.turn_right();
+                    StepError::Overflow => format!("`{}` overflows", instruction),
+                })
+            }
+
+            fn step(&mut self, instruction: &Instruction) -> Result<(), StepError> {
+                let value = instruction.value;
+                let unit = match instruction.direction {
+                    Direction::North => Vector::new(0, 1),
+                    Direction::South => Vector::new(0, -1),
+                    Direction::East => Vector::new(1, 0),
+                    Direction::West => Vector::new(-1, 0),
+                    Direction::Left | Direction::Right => {
+                        let turn = value.rem_euclid(360);
+                        let degrees = if instruction.direction == Direction::Left {
+                            turn
+                        } else {
+                            -turn
+                        };
+                        if self.strict && degrees % 90 != 0 {
+                            return Err(StepError::UnsupportedAngle);
                         }
+                        self.vector = T::rotate(self.vector, degrees)?;
+                        return Ok(());
+                    }
+                    Direction::Forward => {
+                        let moved = self.vector.scale(value).and_then(|v| self.position.add(v));
+                        self.position = moved.ok_or(StepError::Overflow)?;
+                        return Ok(());
                     }
-                    Direction::North => self.position.y += instruction.value,
-                    Direction::South => self.position.y -= instruction.value,
-                    Direction::East => self.position.x += instruction.value,
-                    Direction::West => self.position.x -= instruction.value,
-                    Direction::Forward => self.move_forward(instruction.value),
                 };
+                let target = match self.steering {
+                    Steering::Heading => &mut self.position,
+                    Steering::Waypoint => &mut self.vector,
+                };
+                *target = unit
+                    .scale(value)
+                    .and_then(|shift| target.add(shift))
+                    .ok_or(StepError::Overflow)?;
+                Ok(())
             }
 
-            fn manhattan_distance(&self) -> isize {
-                self.position.x.abs() + self.position.y.abs()
+            // The waypoint's absolute position, or None if it is out of range.
+            pub fn waypoint(&self) -> Option<Vector<T>> {
+                self.position.add(self.vector)
             }
-        }
 
-        #[derive(Debug, Clone, Eq, PartialEq)]
-        pub struct P2State {
-            waypoint: Location,
-            position: Location,
+            pub fn manhattan_distance(&self) -> Option<T> {
+                self.position.manhattan_distance()
+            }
         }
 
-        impl P2State {
-            fn rotate_waypoint_left(&mut self) {
-                swap(&mut self.waypoint.x, &mut self.waypoint.y);
-                self.waypoint.x *= -1;
+        pub fn navigate<T: Coordinate>(
+            navigator: &mut Navigator<T>,
+            instructions: &[Instruction],
+        ) -> Result<(), String> {
+            for (i, instruction) in instructions.iter().enumerate() {
+                navigator
+                    .update(instruction)
+                    .map_err(|e| format!("instruction {}: {}", i + 1, e))?;
             }
+            Ok(())
+        }
+
+        // Ship and absolute waypoint positions, starting before the first instruction and then
+        // after each one. In `Heading` steering the waypoint is one heading ahead of the ship,
+        // clamped to the coordinate range since it is only drawn.
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Route<T> {
+            pub ships: Vec<Vector<T>>,
+            pub waypoints: Vec<Vector<T>>,
+        }
 
-            fn rotate_waypoint_right(&mut self) {
-                swap(&mut self.waypoint.x, &mut self.waypoint.y);
-                self.waypoint.y *= -1;
+        impl<T: Coordinate> Route<T> {
+            fn push(&mut self, navigator: &Navigator<T>) -> Result<(), String> {
+                let waypoint = match navigator.steering {
+                    Steering::Heading => navigator.position.saturating_add(navigator.vector),
+                    Steering::Waypoint => navigator
+                        .waypoint()
+                        .ok_or_else(|| "the waypoint overflows".to_string())?,
+                };
+                self.ships.push(navigator.position);
+                self.waypoints.push(waypoint);
+                Ok(())
             }
 
-            fn move_forward(&mut self, value: isize) {
-                for _i in 0..value {
-                    self.position.x += self.waypoint.x;
-                    self.position.y += self.waypoint.y;
+            // The smallest and largest ship coordinates.
+            pub fn bounding_box(&self) -> (Vector<T>, Vector<T>) {
+                let mut min = self.ships[0];
+                let mut max = self.ships[0];
+                for ship in &self.ships {
+                    if ship.x < min.x {
+                        min.x = ship.x;
+                    }
+                    if ship.y < min.y {
+                        min.y = ship.y;
+                    }
+                    if ship.x > max.x {
+                        max.x = ship.x;
+                    }
+                    if ship.y > max.y {
+                        max.y = ship.y;
+                    }
                 }
+                (min, max)
             }
-        }
 
-        impl State for P2State {
-            fn initialize() -> P2State {
-                P2State {
-                    waypoint: Location { x: 10, y: 1 },
-                    position: Location { x: 0, y: 0 },
-                }
+            // The straight-line length of the ship's track.
+            pub fn distance_sailed(&self) -> f64 {
+                self.ships
+                    .windows(2)
+                    .map(|w| {
+                        (w[1].x.to_f64() - w[0].x.to_f64()).hypot(w[1].y.to_f64() - w[0].y.to_f64())
+                    })
+                    .sum()
             }
 
-            fn update(&mut self, instruction: Instruction) {
-                match instruction.direction {
-                    Direction::North => self.waypoint.y += instruction.value,
-                    Direction::South => self.waypoint.y -= instruction.value,
-                    Direction::East => self.waypoint.x += instruction.value,
-                    Direction::West => self.waypoint.x -= instruction.value,
-                    Direction::Left => {
-                        let num_turns = instruction.value / 90;
-                        for _i in 0..num_turns {
-                            self.rotate_waypoint_left();
-                        }
-                    }
-                    Direction::Right => {
-                        let num_turns = instruction.value / 90;
-                        for _i in 0..num_turns {
-                            self.rotate_waypoint_right();
-                        }
+            // The first ship position with the greatest Manhattan distance from the origin.
+            pub fn furthest(&self) -> Vector<T> {
+                let distance = |p: &Vector<T>| p.x.to_f64().abs() + p.y.to_f64().abs();
+                let mut furthest = self.ships[0];
+                for ship in &self.ships {
+                    if distance(ship) > distance(&furthest) {
+                        furthest = *ship;
                     }
-                    Direction::Forward => self.move_forward(instruction.value),
                 }
+                furthest
             }
 
-            fn manhattan_distance(&self) -> isize {
-                self.position.x.abs() + self.position.y.abs()
+            // North is up. The ship's track is a solid polyline and the waypoint's a dashed one.
+            pub fn to_svg(&self) -> String {
+                let (min, max) = self.bounding_box();
+                let (mut left, mut bottom) = (min.x.to_f64(), min.y.to_f64());
+                let (mut right, mut top) = (max.x.to_f64(), max.y.to_f64());
+                for waypoint in &self.waypoints {
+                    left = left.min(waypoint.x.to_f64());
+                    right = right.max(waypoint.x.to_f64());
+                    bottom = bottom.min(waypoint.y.to_f64());
+                    top = top.max(waypoint.y.to_f64());
+                }
+                let margin = ((right - left).max(top - bottom) / 20.0).max(1.0);
+                let points = |track: &[Vector<T>]| {
+                    track
+                        .iter()
+                        .map(|p| format!("{},{}", p.x, 0.0 - p.y.to_f64()))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                format!(
+                    concat!(
+                        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+                        "  <polyline points=\"{}\" fill=\"none\" stroke=\"grey\" ",
+                        "stroke-dasharray=\"4 4\" vector-effect=\"non-scaling-stroke\"/>\n",
+                        "  <polyline points=\"{}\" fill=\"none\" stroke=\"navy\" ",
+                        "vector-effect=\"non-scaling-stroke\"/>\n",
+                        "  <circle cx=\"0\" cy=\"0\" r=\"{}\" fill=\"red\"/>\n",
+                        "</svg>\n"
+                    ),
+                    left - margin,
+                    0.0 - top - margin,
+                    right - left + 2.0 * margin,
+                    top - bottom + 2.0 * margin,
+                    points(&self.waypoints),
+                    points(&self.ships),
+                    margin / 4.0
+                )
             }
         }
 
-        pub fn solve(problem: super::Problem, file: &str) -> Option<isize> {
-            let instructions: Vec<Instruction> = super::lines_from_file(file)
-                .iter()
-                .map(|x| Instruction::new(x))
-                .collect();
-
-            let mut state: Box<dyn State> = match problem {
-                super::Problem::One => Box::new(P1State::initialize()),
-                super::Problem::Two => Box::new(P2State::initialize()),
+        // Like `navigate`, but keeps every position along the way. On error the navigator is
+        // left after the last instruction that was recorded.
+        pub fn record<T: Coordinate>(
+            navigator: &mut Navigator<T>,
+            instructions: &[Instruction],
+        ) -> Result<Route<T>, String> {
+            let mut route = Route {
+                ships: vec![],
+                waypoints: vec![],
             };
-
-            for instruction in instructions {
-                state.update(instruction);
+            route.push(navigator)?;
+            for (i, instruction) in instructions.iter().enumerate() {
+                let mut next = navigator.clone();
+                next.update(instruction)
+                    .and_then(|_| route.push(&next))
+                    .map_err(|e| format!("instruction {}: {}", i + 1, e))?;
+                *navigator = next;
             }
+            Ok(route)
+        }
+
+        pub fn instructions_from_file(file: &str) -> Vec<Instruction> {
+            super::lines_from_file(file)
+                .iter()
+                .map(|x| Instruction::new(x))
+                .collect()
+        }
 
-            Some(state.manhattan_distance())
+        pub fn solve(problem: super::Problem, file: &str) -> Option<isize> {
+            let instructions = instructions_from_file(file);
+            let mut navigator = match problem {
+                super::Problem::One => Navigator::new(Steering::Heading),
+                super::Problem::Two => Navigator::new(Steering::Waypoint),
+            };
+            navigate(&mut navigator, &instructions).ok()?;
+            navigator.manhattan_distance()
         }
     }
 
@@ -1280,6 +4143,7 @@ pub mod aoc {
             Some(largest_bus_id_bus.id * n - largest_bus_id_bus.position)
         }
 
+        #[allow(clippy::manual_is_multiple_of)]
         fn check_all_buses(bus_list: &[P2Bus], t: usize) -> bool {
             for bus in bus_list {
                 if (t + bus.position) % bus.id != 0 {
@@ -1292,140 +4156,391 @@ pub mod aoc {
 
     pub mod day_fourteen {
         use crate::aoc::lines_from_file;
-        use itertools::Itertools;
-        use std::collections::HashMap;
+        use std::convert::TryFrom;
 
-        struct Mask {
-            save: usize,
-            transfer: usize,
+        // Each bit of a `width`-bit mask is in exactly one of `ones`, `zeros` or `floating`.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub struct Mask {
+            pub ones: u64,
+            pub zeros: u64,
+            pub floating: u64,
         }
 
-        struct Memory {
-            address: usize,
-            value: usize,
+        impl Mask {
+            pub fn parse(mask: &str, width: u32) -> Result<Mask, String> {
+                if mask.chars().count() != width as usize {
+                    return Err(format!(
+                        "mask `{}` has {} bits, expected {}",
+                        mask,
+                        mask.chars().count(),
+                        width
+                    ));
+                }
+                let mut parsed = Mask {
+                    ones: 0,
+                    zeros: 0,
+                    floating: 0,
+                };
+                for (i, c) in mask.chars().enumerate() {
+                    let bit = 1 << (width as usize - 1 - i);
+                    match c {
+                        '1' => parsed.ones |= bit,
+                        '0' => parsed.zeros |= bit,
+                        'X' => parsed.floating |= bit,
+                        _ => return Err(format!("mask `{}` has `{}` at bit {}", mask, c, i)),
+                    }
+                }
+                Ok(parsed)
+            }
         }
 
-        struct MaskP2 {
-            xs_zeroed: usize,
-            x_positions: Vec<usize>,
+        // Every address that equals `fixed` outside the `floating` bits.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub struct Pattern {
+            pub fixed: u64,
+            pub floating: u64,
         }
 
-        pub fn solve(problem: super::Problem, filename: &str) -> Option<usize> {
-            let input = lines_from_file(filename);
-            match problem {
-                super::Problem::One => solve_p1(&input),
-                super::Problem::Two => solve_p2(&input),
-            }
-        }
-
-        fn parse_mask_p1(line: &str) -> Mask {
-            let mask = line
-                .strip_prefix("mask = ")
-                .expect("Error parsing mask assignment instruction.");
-            let transfer = usize::from_str_radix(&*mask.replace("X", "0"), 2)
-                .expect("Error parsing mask string to usize.");
-            let save = usize::from_str_radix(
-                &*mask
-                    .replace("0", "Z")
-                    .replace("1", "Z")
-                    .replace("X", "1")
-                    .replace("Z", "0"),
-                2,
-            )
-            .expect("Error parsing mask string to usize.");
-            Mask { save, transfer }
+        impl Pattern {
+            pub fn new(address: u64, floating: u64) -> Pattern {
+                Pattern {
+                    fixed: address & !floating,
+                    floating,
+                }
+            }
+
+            pub fn address_count(&self) -> u128 {
+                1 << self.floating.count_ones()
+            }
+
+            pub fn contains(&self, address: u64) -> bool {
+                address & !self.floating == self.fixed
+            }
+
+            pub fn render(&self, width: u32) -> String {
+                (0..width)
+                    .rev()
+                    .map(
+                        |bit| match (self.floating >> bit & 1, self.fixed >> bit & 1) {
+                            (1, _) => 'X',
+                            (_, 1) => '1',
+                            _ => '0',
+                        },
+                    )
+                    .collect()
+            }
+
+            pub fn intersect(&self, other: &Pattern) -> Option<Pattern> {
+                let both_fixed = !self.floating & !other.floating;
+                if (self.fixed ^ other.fixed) & both_fixed != 0 {
+                    return None;
+                }
+                Some(Pattern {
+                    fixed: self.fixed | other.fixed,
+                    floating: self.floating & other.floating,
+                })
+            }
+
+            // Disjoint patterns covering the addresses in `self` but not in `other`. Each bit
+            // that floats here but is fixed in `other` splits off the half `other` misses.
+            pub fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+                if self.intersect(other).is_none() {
+                    return vec![*self];
+                }
+                let mut pieces = vec![];
+                let mut rest = *self;
+                let mut splits = self.floating & !other.floating;
+                while splits != 0 {
+                    let bit = splits & splits.wrapping_neg();
+                    splits &= !bit;
+                    rest.floating &= !bit;
+                    pieces.push(Pattern {
+                        fixed: rest.fixed | (!other.fixed & bit),
+                        floating: rest.floating,
+                    });
+                    rest.fixed |= other.fixed & bit;
+                }
+                pieces
+            }
         }
 
-        fn solve_p1(input: &[String]) -> Option<usize> {
-            let mut mask = Mask {
-                save: 0,
-                transfer: 0,
-            };
-            let mut mem = HashMap::new();
-
-            for line in input {
-                if line.contains("mask") {
-                    mask = parse_mask_p1(&line);
-                } else if line.contains("mem") {
-                    let memory = parse_assignment(&line);
-                    mem.insert(memory.address, (memory.value & mask.save) ^ mask.transfer);
-                } else {
-                    panic!("Input instructions are ill-formatted.")
+        // Part-two memory: disjoint patterns with the value written to all of their addresses.
+        #[derive(Debug, Clone, Default, Eq, PartialEq)]
+        pub struct FloatingMemory {
+            pub writes: Vec<(Pattern, u64)>,
+        }
+
+        impl FloatingMemory {
+            pub fn write(&mut self, pattern: Pattern, value: u64) {
+                let mut writes = vec![];
+                for (old, old_value) in &self.writes {
+                    writes.extend(old.subtract(&pattern).into_iter().map(|p| (p, *old_value)));
                 }
+                writes.push((pattern, value));
+                self.writes = writes;
+            }
+
+            pub fn get(&self, address: u64) -> Option<u64> {
+                self.writes
+                    .iter()
+                    .find(|(p, _)| p.contains(address))
+                    .map(|w| w.1)
+            }
+
+            pub fn sum(&self) -> u128 {
+                self.writes
+                    .iter()
+                    .map(|(p, v)| p.address_count() * *v as u128)
+                    .sum()
             }
-            Some(mem.values().sum())
         }
 
-        fn solve_p2(input: &[String]) -> Option<usize> {
-            let mut mask = MaskP2 {
-                xs_zeroed: 0,
-                x_positions: vec![],
-            };
-            let mut mem = HashMap::new();
-
-            for line in input {
-                if line.contains("mask") {
-                    mask = parse_mask_p2(&line);
-                } else if line.contains("mem") {
-                    let mut memory = parse_assignment(&line);
-                    // First we zero out the x's of the mask (which is done by parse_mask_p2 and
-                    // returned as part of mask) and combine that with the address.
-                    memory.address |= mask.xs_zeroed;
-                    // Then we zero out the positions where the x's should have been.
-                    for pos in &mask.x_positions {
-                        memory.address &= !(1 << pos);
-                    }
-                    let addresses = generate_addresses(&mask, &memory);
-                    for address in addresses {
-                        mem.insert(address, memory.value);
+        // What one mask character does to the matching bit.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub enum BitRule {
+            Keep,
+            Set,
+            Clear,
+            Float,
+        }
+
+        // Rules for the mask characters `0`, `1` and `X`, in that order.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub struct BitRules(pub [BitRule; 3]);
+
+        impl BitRules {
+            fn parse(rules: &str) -> Result<BitRules, String> {
+                let parsed: Result<Vec<BitRule>, String> = rules
+                    .chars()
+                    .map(|c| match c {
+                        'k' => Ok(BitRule::Keep),
+                        's' => Ok(BitRule::Set),
+                        'c' => Ok(BitRule::Clear),
+                        'f' => Ok(BitRule::Float),
+                        _ => Err(format!("unknown bit rule `{}`", c)),
+                    })
+                    .collect();
+                let parsed = parsed?;
+                if parsed.len() != 3 {
+                    return Err(format!("`{}` needs one rule for each of 0, 1 and X", rules));
+                }
+                Ok(BitRules([parsed[0], parsed[1], parsed[2]]))
+            }
+
+            fn bits(&self, mask: &Mask, rule: BitRule) -> u64 {
+                [mask.zeros, mask.ones, mask.floating]
+                    .iter()
+                    .zip(self.0.iter())
+                    .filter(|(_, &r)| r == rule)
+                    .fold(0, |bits, (b, _)| bits | b)
+            }
+
+            fn apply(&self, mask: &Mask, word: u64) -> Pattern {
+                let set = self.bits(mask, BitRule::Set);
+                let clear = self.bits(mask, BitRule::Clear);
+                Pattern::new((word | set) & !clear, self.bits(mask, BitRule::Float))
+            }
+        }
+
+        // How a mask rewrites the address and value of each write. Values cannot float.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub struct Decoder {
+            pub address: BitRules,
+            pub value: BitRules,
+        }
+
+        impl Decoder {
+            pub fn version_one() -> Decoder {
+                Decoder::parse("address=kkk value=csk").expect("Version one spec is ill-formatted.")
+            }
+
+            pub fn version_two() -> Decoder {
+                Decoder::parse("address=ksf value=kkk").expect("Version two spec is ill-formatted.")
+            }
+
+            // `v1`, `v2`, or `address=<rules> value=<rules>` where each of the three rules is
+            // `k`eep, `s`et, `c`lear or `f`loat.
+            pub fn parse(spec: &str) -> Result<Decoder, String> {
+                match spec {
+                    "v1" => return Ok(Decoder::version_one()),
+                    "v2" => return Ok(Decoder::version_two()),
+                    _ => (),
+                }
+                let mut address = None;
+                let mut value = None;
+                for part in spec.split_whitespace() {
+                    match part.split_once('=') {
+                        Some(("address", rules)) => address = Some(BitRules::parse(rules)?),
+                        Some(("value", rules)) => value = Some(BitRules::parse(rules)?),
+                        _ => return Err(format!("`{}` is not address=... or value=...", part)),
                     }
-                } else {
-                    panic!("Input instructions are ill-formatted.")
                 }
+                let keep = BitRules([BitRule::Keep; 3]);
+                let decoder = Decoder {
+                    address: address.unwrap_or(keep),
+                    value: value.unwrap_or(keep),
+                };
+                if decoder.value.0.contains(&BitRule::Float) {
+                    return Err("values cannot float".to_string());
+                }
+                Ok(decoder)
+            }
+
+            pub fn decode(&self, mask: &Mask, address: u64, value: u64) -> (Pattern, u64) {
+                (
+                    self.address.apply(mask, address),
+                    self.value.apply(mask, value).fixed,
+                )
             }
-            Some(mem.values().sum())
         }
 
-        fn generate_addresses(mask: &MaskP2, memory: &Memory) -> Vec<usize> {
-            let mask_list: Vec<usize> = mask.x_positions.iter().map(|x| 1 << x).collect();
-            let mut addresses: Vec<usize> = vec![];
-            for i in 0..mask_list.len() {
-                addresses.extend(
-                    mask_list
-                        .iter()
-                        .combinations(i + 1)
-                        .map(|v| v.into_iter().sum::<usize>()),
-                );
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub enum Instruction {
+            SetMask(Mask),
+            Write { address: u64, value: u64 },
+        }
+
+        impl Instruction {
+            pub fn parse(line: &str, width: u32) -> Result<Instruction, String> {
+                if let Some(mask) = line.strip_prefix("mask = ") {
+                    return Ok(Instruction::SetMask(Mask::parse(mask, width)?));
+                }
+                let (address, value) = line
+                    .strip_prefix("mem[")
+                    .and_then(|rest| rest.split_once("] = "))
+                    .ok_or_else(|| format!("`{}` is not a mask or memory assignment", line))?;
+                let address = address
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a memory address", address))?;
+                let value = value
+                    .parse()
+                    .map_err(|_| format!("`{}` is not a value", value))?;
+                if width < 64 && address >> width != 0 {
+                    return Err(format!("address {} is wider than {} bits", address, width));
+                }
+                Ok(Instruction::Write { address, value })
             }
-            addresses.iter_mut().for_each(|x| *x += memory.address);
-            addresses.push(memory.address);
-            addresses
         }
 
-        fn parse_mask_p2(line: &str) -> MaskP2 {
-            let mask = line
-                .strip_prefix("mask = ")
-                .expect("Error parsing mask assignment instruction.");
-            let xs_zeroed = usize::from_str_radix(&*mask.replace("X", "0"), 2)
-                .expect("Error parsing mask string to usize.");
-            let x_positions = mask.match_indices('X').map(|x| 35 - x.0).collect();
-            MaskP2 {
-                xs_zeroed,
-                x_positions,
+        // The addresses a write reached and the value stored there. Masks leave both as None.
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub struct Step {
+            pub line: usize,
+            pub instruction: Instruction,
+            pub addresses: Option<Pattern>,
+            pub value: Option<u64>,
+        }
+
+        // Until the first mask, writes store their value at their address unchanged, which is
+        // what an all-`0` mask does under version 2.
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub struct DecoderChip {
+            pub width: u32,
+            pub decoder: Decoder,
+            pub mask: Option<Mask>,
+            pub memory: FloatingMemory,
+            pub trace: Vec<Step>,
+        }
+
+        impl DecoderChip {
+            pub fn new(width: u32, decoder: Decoder) -> Result<DecoderChip, String> {
+                if width == 0 || width > 64 {
+                    return Err(format!("a word needs between 1 and 64 bits, not {}", width));
+                }
+                Ok(DecoderChip {
+                    width,
+                    decoder,
+                    mask: None,
+                    memory: FloatingMemory::default(),
+                    trace: vec![],
+                })
+            }
+
+            pub fn execute(&mut self, line: usize, instruction: Instruction) -> Step {
+                let mut step = Step {
+                    line,
+                    instruction,
+                    addresses: None,
+                    value: None,
+                };
+                match instruction {
+                    Instruction::SetMask(mask) => self.mask = Some(mask),
+                    Instruction::Write { address, value } => {
+                        let (pattern, value) = match &self.mask {
+                            Some(mask) => self.decoder.decode(mask, address, value),
+                            None => (Pattern::new(address, 0), value),
+                        };
+                        self.memory.write(pattern, value);
+                        step.addresses = Some(pattern);
+                        step.value = Some(value);
+                    }
+                }
+                self.trace.push(step);
+                step
+            }
+
+            pub fn run(&mut self, program: &[String]) -> Result<(), String> {
+                for (i, line) in program.iter().enumerate() {
+                    let instruction = Instruction::parse(line, self.width)
+                        .map_err(|e| format!("line {}: {}", i + 1, e))?;
+                    self.execute(i + 1, instruction);
+                }
+                Ok(())
+            }
+
+            pub fn sum(&self) -> u128 {
+                self.memory.sum()
+            }
+
+            // One CSV row per stored pattern, ordered by its lowest address. Patterns are
+            // written like masks, with `X` for floating bits.
+            pub fn dump(&self) -> String {
+                let mut writes = self.memory.writes.clone();
+                writes.sort_by_key(|(p, _)| p.fixed);
+                let mut csv = String::from("addresses,count,value\n");
+                for (pattern, value) in writes {
+                    csv.push_str(&format!(
+                        "{},{},{}\n",
+                        pattern.render(self.width),
+                        pattern.address_count(),
+                        value
+                    ));
+                }
+                csv
+            }
+
+            // One line per instruction, with the addresses and value of each write.
+            pub fn trace_text(&self) -> String {
+                self.trace
+                    .iter()
+                    .map(
+                        |step| match (step.instruction, step.addresses, step.value) {
+                            (Instruction::Write { address, value }, Some(p), Some(stored)) => {
+                                format!(
+                                    "{}: mem[{}] = {} -> {} ({} addresses) = {}\n",
+                                    step.line,
+                                    address,
+                                    value,
+                                    p.render(self.width),
+                                    p.address_count(),
+                                    stored
+                                )
+                            }
+                            _ => format!("{}: mask\n", step.line),
+                        },
+                    )
+                    .collect()
             }
         }
 
-        fn parse_assignment(line: &str) -> Memory {
-            let memory_instruction: Vec<&str> = line.split("] = ").collect();
-            let address = memory_instruction[0]
-                .strip_prefix("mem[")
-                .expect("Memory assignment line is ill-formatted.")
-                .parse()
-                .expect("Error parsing memory address.");
-            let value = memory_instruction[1]
-                .parse()
-                .expect("Error parsing value assigned to memory address.");
-            Memory { address, value }
+        pub fn solve(problem: super::Problem, filename: &str) -> Option<usize> {
+            let decoder = match problem {
+                super::Problem::One => Decoder::version_one(),
+                super::Problem::Two => Decoder::version_two(),
+            };
+            let mut chip = DecoderChip::new(36, decoder).ok()?;
+            chip.run(&lines_from_file(filename)).ok()?;
+            usize::try_from(chip.sum()).ok()
         }
     }
 
@@ -1487,7 +4602,8 @@ pub mod aoc {
     pub mod day_sixteen {
         use crate::aoc::lines_from_file;
         use itertools::Itertools;
-        use std::collections::HashSet;
+        use std::collections::{HashSet, VecDeque};
+        use std::fmt;
 
         #[derive(Debug, Clone, Eq, PartialEq)]
         struct ValidRange {
@@ -1530,19 +4646,16 @@ pub mod aoc {
                 }
             }
 
+            if let super::Problem::One = problem {
+                return Some(invalid_values.iter().sum());
+            }
+
             let valid_tickets: Vec<&Vec<usize>> = collected_info
                 .nearby_tickets
                 .iter()
                 .filter(|&x| !invalid_tickets.contains(x))
                 .collect();
-            let all_possible_label_positions = calculate_positions(&valid_tickets, &collected_info);
-            let mut refined_possible_label_positions = refine_positions(
-                &all_possible_label_positions,
-                &collected_info,
-                valid_tickets.len(),
-            );
-            let labels =
-                calculate_final_labels(&collected_info, &mut refined_possible_label_positions);
+            let labels = assign_labels(&collected_info, &valid_tickets).ok()?;
 
             let mut total = 1;
             for (i, label) in labels.iter().enumerate() {
@@ -1550,76 +4663,160 @@ pub mod aoc {
                     total *= collected_info.my_ticket[i];
                 }
             }
+            Some(total)
+        }
 
-            match problem {
-                super::Problem::One => Some(invalid_values.iter().sum()),
-                super::Problem::Two => Some(total),
-            }
+        #[derive(Debug, Clone, Eq, PartialEq)]
+        pub enum AssignmentError {
+            // The columns left without a field in a largest matching.
+            NoAssignment(Vec<usize>),
+            // The columns whose field differs between valid assignments.
+            Ambiguous(Vec<usize>),
         }
 
-        fn calculate_positions(
-            valid_tickets: &[&Vec<usize>],
-            info: &Info,
-        ) -> Vec<Vec<HashSet<String>>> {
-            let mut result: Vec<Vec<HashSet<String>>> = vec![];
-            for ticket in valid_tickets {
-                result.push(vec![]);
-                for num in ticket.iter() {
-                    let valid_set: HashSet<String> = info
-                        .categories
-                        .iter()
-                        .filter(|c| c.is_num_valid(num))
-                        .map(|c| c.name.to_string())
-                        .collect();
-                    result.last_mut().unwrap().push(valid_set);
-                }
+        impl fmt::Display for AssignmentError {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let (message, columns) = match self {
+                    AssignmentError::NoAssignment(columns) => {
+                        ("no valid assignment, unmatched columns", columns)
+                    }
+                    AssignmentError::Ambiguous(columns) => {
+                        ("multiple assignments possible, ambiguous columns", columns)
+                    }
+                };
+                write!(f, "{}: {}", message, columns.iter().join(", "))
             }
-            result
         }
 
-        fn refine_positions(
-            possible_positions: &[Vec<HashSet<String>>],
+        // Reads a notes file and names the field in each column of the tickets.
+        pub fn field_assignment(filename: &str) -> Result<Vec<String>, AssignmentError> {
+            let info = parse_input(&lines_from_file(filename));
+            let valid_tickets: Vec<&Vec<usize>> = info
+                .nearby_tickets
+                .iter()
+                .filter(|t| t.iter().all(|&n| valid_number(n, &info.categories)))
+                .collect();
+            assign_labels(&info, &valid_tickets)
+        }
+
+        fn assign_labels(
             info: &Info,
-            num_valid_tickets: usize,
-        ) -> Vec<HashSet<String>> {
-            let mut reduced_set = vec![];
-            for i in 0..info.categories.len() {
-                let mut set = possible_positions[0][i].to_owned();
-                for j in 0..num_valid_tickets - 1 {
-                    set = set
-                        .intersection(&possible_positions[j + 1][i])
-                        .cloned()
-                        .collect();
+            valid_tickets: &[&Vec<usize>],
+        ) -> Result<Vec<String>, AssignmentError> {
+            let candidates: Vec<Vec<usize>> = (0..info.my_ticket.len())
+                .map(|column| {
+                    (0..info.categories.len())
+                        .filter(|&c| {
+                            valid_tickets
+                                .iter()
+                                .all(|t| info.categories[c].is_num_valid(&t[column]))
+                        })
+                        .collect()
+                })
+                .collect();
+            let fields = match_fields(&candidates, info.categories.len())?;
+            Ok(fields
+                .iter()
+                .map(|&c| info.categories[c].name.to_string())
+                .collect())
+        }
+
+        // Gives every column a distinct field, where `candidates[column]` lists the fields it
+        // accepts. The assignment must exist and be the only one.
+        pub fn match_fields(
+            candidates: &[Vec<usize>],
+            fields: usize,
+        ) -> Result<Vec<usize>, AssignmentError> {
+            let matching = hopcroft_karp(candidates, fields);
+            let unmatched: Vec<usize> = (0..candidates.len())
+                .filter(|&c| matching[c].is_none())
+                .collect();
+            if !unmatched.is_empty() {
+                return Err(AssignmentError::NoAssignment(unmatched));
+            }
+            let matching: Vec<usize> = matching.into_iter().flatten().collect();
+
+            // A column is ambiguous when the columns still match up without its current field.
+            let mut ambiguous = vec![];
+            for (column, &field) in matching.iter().enumerate() {
+                let mut without = candidates.to_vec();
+                without[column].retain(|&f| f != field);
+                if hopcroft_karp(&without, fields).iter().all(|m| m.is_some()) {
+                    ambiguous.push(column);
                 }
-                reduced_set.push(set);
             }
-            reduced_set
+            if !ambiguous.is_empty() {
+                return Err(AssignmentError::Ambiguous(ambiguous));
+            }
+            Ok(matching)
         }
 
-        fn calculate_final_labels(
-            info: &Info,
-            possible_label_positions: &mut Vec<HashSet<String>>,
-        ) -> Vec<String> {
-            let mut labels: Vec<String> = vec![];
-            for _ in 0..info.categories.len() {
-                labels.push("".to_string());
-            }
+        // A maximum matching from columns to fields, grown by shortest augmenting paths.
+        fn hopcroft_karp(candidates: &[Vec<usize>], fields: usize) -> Vec<Option<usize>> {
+            let mut column_match = vec![None; candidates.len()];
+            let mut field_match = vec![None; fields];
             loop {
-                if !labels.iter().any(|s| s.is_empty()) {
-                    break;
+                let mut layer = vec![usize::MAX; candidates.len()];
+                let mut queue = VecDeque::new();
+                for (column, matched) in column_match.iter().enumerate() {
+                    if matched.is_none() {
+                        layer[column] = 0;
+                        queue.push_back(column);
+                    }
+                }
+                let mut found = false;
+                while let Some(column) = queue.pop_front() {
+                    for &field in &candidates[column] {
+                        match field_match[field] {
+                            None => found = true,
+                            Some(next) if layer[next] == usize::MAX => {
+                                layer[next] = layer[column] + 1;
+                                queue.push_back(next);
+                            }
+                            Some(_) => (),
+                        }
+                    }
                 }
-                for (i, possible_labels) in possible_label_positions.iter().enumerate() {
-                    if possible_labels.len() == 1 {
-                        labels[i] = possible_labels.iter().collect_vec()[0].to_string();
+                if !found {
+                    return column_match;
+                }
+                for column in 0..candidates.len() {
+                    if column_match[column].is_none() {
+                        augment(
+                            column,
+                            candidates,
+                            &mut layer,
+                            &mut column_match,
+                            &mut field_match,
+                        );
                     }
                 }
-                for label in &labels {
-                    for elem in possible_label_positions.iter_mut() {
-                        elem.remove(label);
+            }
+        }
+
+        fn augment(
+            column: usize,
+            candidates: &[Vec<usize>],
+            layer: &mut [usize],
+            column_match: &mut [Option<usize>],
+            field_match: &mut [Option<usize>],
+        ) -> bool {
+            for &field in &candidates[column] {
+                let free = match field_match[field] {
+                    None => true,
+                    Some(next) => {
+                        layer[next] == layer[column] + 1
+                            && augment(next, candidates, layer, column_match, field_match)
                     }
+                };
+                if free {
+                    column_match[column] = Some(field);
+                    field_match[field] = Some(column);
+                    return true;
                 }
             }
-            labels
+            layer[column] = usize::MAX;
+            false
         }
 
         fn valid_number(num: usize, categories: &[Category]) -> bool {
@@ -1646,7 +4843,7 @@ pub mod aoc {
                     let name = range_definition[0].to_string();
                     let valid_ranges = range_definition[1]
                         .split(" or ")
-                        .map(|s| parse_range(s))
+                        .map(parse_range)
                         .collect_tuple()
                         .expect("Error parsing range.");
                     categories.push(Category { name, valid_ranges });
@@ -1758,7 +4955,7 @@ pub mod aoc {
         fn parse_initial_state(problem: super::Problem, input: &[String]) -> BTreeSet<Point<isize>> {
             let mut active = BTreeSet::new();
             for (y, line) in input.iter().enumerate() {
-                for (x, char) in line.chars().into_iter().enumerate() {
+                for (x, char) in line.chars().enumerate() {
                     if char == '#' {
                         match problem {
                             super::Problem::One => {
@@ -1816,8 +5013,98 @@ mod tests {
         let filename = "./misc/D01.txt";
         let p1 = aoc::day_one::solve(aoc::Problem::One, filename);
         let p2 = aoc::day_one::solve(aoc::Problem::Two, filename);
-        assert_eq!(p1, 800139);
-        assert_eq!(p2, 59885340);
+        assert_eq!(p1, Some(800139));
+        assert_eq!(p2, Some(59885340));
+    }
+
+    #[test]
+    fn day_one_k_sum() {
+        use aoc::day_one::{k_sum, Solution};
+
+        let report = [1721, 979, 366, 299, 675, 1456];
+        assert_eq!(
+            k_sum(&report, 2, 2020),
+            Some(Solution {
+                entries: vec![1721, 299],
+                indices: vec![0, 3]
+            })
+        );
+        assert_eq!(
+            k_sum(&report, 3, 2020),
+            Some(Solution {
+                entries: vec![979, 366, 675],
+                indices: vec![1, 2, 4]
+            })
+        );
+        assert_eq!(k_sum(&report, 2, 1), None);
+        assert_eq!(
+            k_sum(&report, 4, 1721 + 979 + 366 + 1456).map(|s| s.indices),
+            Some(vec![0, 1, 2, 5])
+        );
+
+        assert_eq!(
+            k_sum(&[1010, 5, 1010], 2, 2020).map(|s| s.indices),
+            Some(vec![0, 2])
+        );
+        assert_eq!(k_sum(&[1010, 5, 7], 2, 2020), None);
+        assert_eq!(
+            k_sum(&[5, 1010, 5, 1010], 3, 1020).map(|s| s.entries),
+            Some(vec![5, 5, 1010])
+        );
+    }
+
+    #[test]
+    fn day_one_subsets() {
+        use aoc::day_one::{any_subset, count_subsets, subsets};
+
+        let report = [1721, 979, 366, 299, 675, 1456];
+        assert_eq!(count_subsets(&report, 2020, Some(2)), Ok(1));
+        assert_eq!(count_subsets(&report, 2020, Some(3)), Ok(1));
+        assert_eq!(count_subsets(&report, 2020, None), Ok(2));
+        let found: Vec<Vec<usize>> = subsets(&report, 2020, None)
+            .unwrap()
+            .map(|s| s.indices)
+            .collect();
+        assert_eq!(found, vec![vec![0, 3], vec![1, 2, 4]]);
+        assert_eq!(
+            any_subset(&report, 366 + 675).map(|s| s.entries),
+            Some(vec![366, 675])
+        );
+        assert_eq!(any_subset(&report, 1), None);
+        assert_eq!(count_subsets(&report, -5, None), Ok(0));
+        assert_eq!(
+            count_subsets(&[3, -1], 2, None),
+            Err("subset sums need non-negative entries, not -1".to_string())
+        );
+        assert!(subsets(&[3, -1], 2, Some(1)).is_err());
+        assert_eq!(any_subset(&[3, -1], 2), None);
+
+        let large: Vec<i32> = (0..300).map(|i| 1_000_000 + i).collect();
+        assert_eq!(
+            count_subsets(&large, 10_000_000, Some(10)),
+            Err("a subset-sum table holds at most 16777216 cells".to_string())
+        );
+        assert!(subsets(&large, 10_000_000, Some(10)).is_err());
+        assert!(count_subsets(&[1], 1, Some(usize::MAX)).is_err());
+
+        // Every subset of 1s of size 3 out of 300 sums to 3: C(300, 3) of them.
+        let ones = vec![1; 300];
+        assert_eq!(count_subsets(&ones, 3, Some(3)), Ok(4455100));
+        assert_eq!(count_subsets(&ones, 0, None), Ok(1));
+        assert_eq!(subsets(&ones, 2, Some(2)).unwrap().take(1000).count(), 1000);
+
+        let entries: Vec<i32> = std::fs::read_to_string("./misc/D01.txt")
+            .expect("Could not read file.")
+            .split_whitespace()
+            .map(|s| s.parse().expect("parse error"))
+            .collect();
+        assert_eq!(count_subsets(&entries, 2020, Some(2)), Ok(1));
+        assert_eq!(count_subsets(&entries, 2020, Some(3)), Ok(1));
+        let pair = subsets(&entries, 2020, Some(2))
+            .unwrap()
+            .next()
+            .expect("No pair found.");
+        assert_eq!(pair.entries.iter().product::<i32>(), 800139);
     }
 
     #[test]
@@ -1829,6 +5116,145 @@ mod tests {
         assert_eq!(p2, 711);
     }
 
+    #[test]
+    fn day_two_policies() {
+        use aoc::day_two::{count_valid, parse_policy, PasswordInfo};
+
+        let entries: Vec<PasswordInfo> = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc"]
+            .iter()
+            .map(|line| PasswordInfo::new(line))
+            .collect();
+        let verdicts = |spec: &str| -> Vec<bool> {
+            let policy = parse_policy(spec).expect("Could not parse policy.");
+            entries.iter().map(|e| policy.is_valid(e)).collect()
+        };
+        assert_eq!(verdicts("count-range"), vec![true, false, true]);
+        assert_eq!(verdicts("positional-xor"), vec![true, false, false]);
+        assert_eq!(verdicts("positional-and"), vec![false, false, true]);
+        assert_eq!(verdicts("[a-c]{3,}"), vec![true, false, true]);
+        assert_eq!(verdicts("[^c]{0}"), vec![false, false, true]);
+        assert_eq!(verdicts("[cde]{2}"), vec![false, false, false]);
+        assert_eq!(
+            verdicts("and(count-range, not(positional-and))"),
+            vec![true, false, false]
+        );
+        assert_eq!(
+            verdicts("or(positional-xor,[g]{1,})"),
+            vec![true, true, false]
+        );
+
+        let policy = parse_policy("and(count-range,or([a-z]{1,2},not(positional-xor)))").unwrap();
+        assert_eq!(
+            policy.name(),
+            "and(count-range,or([a-z]{1,2},not(positional-xor)))"
+        );
+        assert!(parse_policy("length").is_err());
+        assert!(parse_policy("not(count-range,positional-xor)").is_err());
+        assert!(parse_policy("[z-a]").is_err());
+        assert_eq!(
+            parse_policy("[a-z]{5,2}").err(),
+            Some("Quantifier `{5,2}` is backwards.".to_string())
+        );
+        assert!(parse_policy("[a-z]{2,2}").is_ok());
+        assert!(parse_policy("and(count-range").is_err());
+
+        let filename = "./misc/D02.txt";
+        let either = parse_policy("or(count-range,positional-xor)").unwrap();
+        let both = parse_policy("and(count-range,positional-xor)").unwrap();
+        assert_eq!(
+            count_valid(filename, either.as_ref()) + count_valid(filename, both.as_ref()),
+            515 + 711
+        );
+    }
+
+    #[test]
+    fn day_two_audit() {
+        use aoc::day_two::{
+            audit_lines, audit_to_csv, audit_to_json, parse_policy, PasswordPolicy,
+        };
+
+        let lines: Vec<String> = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccc", "x-3 a: aa"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let policies: Vec<Box<dyn PasswordPolicy>> = vec![
+            parse_policy("count-range").unwrap(),
+            parse_policy("positional-xor").unwrap(),
+            parse_policy("and(positional-and,[a-z]{6,})").unwrap(),
+        ];
+        let audit = audit_lines(&lines, &policies);
+        let reasons: Vec<Vec<Option<String>>> = audit
+            .iter()
+            .map(|line| {
+                line.verdicts
+                    .iter()
+                    .map(|v| v.result.clone().err())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(reasons[0][..2], [None, None]);
+        assert_eq!(
+            reasons[0][2],
+            Some(
+                "letter 'a' not at position 3; 5 characters match [a-z], allowed 6 or more"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            reasons[1][0],
+            Some("letter 'b' appears 0 times, allowed 1-3".to_string())
+        );
+        assert_eq!(
+            reasons[1][1],
+            Some("letter 'b' at neither of positions 1 and 3".to_string())
+        );
+        assert_eq!(reasons[2][1], None);
+        assert_eq!(
+            reasons[2][2],
+            Some(
+                "position 9 out of range; 5 characters match [a-z], allowed 6 or more".to_string()
+            )
+        );
+        assert_eq!(
+            audit[3].entry,
+            Err("could not parse range minimum `x`".to_string())
+        );
+        assert!(audit[3].verdicts.is_empty());
+
+        let csv = audit_to_csv(&audit);
+        let csv_lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            csv_lines[0],
+            "line,min,max,letter,password,error,count-range,count-range reason,\
+             positional-xor,positional-xor reason,\"and(positional-and,[a-z]{6,})\",\
+             \"and(positional-and,[a-z]{6,}) reason\""
+        );
+        assert_eq!(
+            csv_lines[2],
+            "2,1,3,b,cdefg,,invalid,\"letter 'b' appears 0 times, allowed 1-3\",\
+             invalid,letter 'b' at neither of positions 1 and 3,\
+             invalid,\"letter 'b' not at position 1; 5 characters match [a-z], allowed 6 or more\""
+        );
+        assert_eq!(
+            csv_lines[4],
+            "4,,,,,could not parse range minimum `x`,,,,,,"
+        );
+        assert_eq!(
+            audit_to_csv(&audit[3..]),
+            "line,min,max,letter,password,error\n\
+             4,,,,,could not parse range minimum `x`\n"
+        );
+
+        let json = audit_to_json(&audit);
+        assert!(json.starts_with(
+            "[{\"line\":1,\"entry\":{\"min\":1,\"max\":3,\"letter\":\"a\",\"password\":\"abcde\"},\
+             \"error\":null,\"verdicts\":[{\"policy\":\"count-range\",\"valid\":true,\"reason\":null},"
+        ));
+        assert!(json.ends_with(
+            "{\"line\":4,\"entry\":null,\"error\":\"could not parse range minimum `x`\",\"verdicts\":[]}]"
+        ));
+    }
+
     #[test]
     fn day_three() {
         let filename = "./misc/D03.txt";
@@ -1838,6 +5264,108 @@ mod tests {
         assert_eq!(p2, 2138320800);
     }
 
+    #[test]
+    fn day_three_map() {
+        use aoc::day_three::{Slope, TobogganMap, PART_TWO_SLOPES};
+
+        let lines: Vec<String> = [
+            "..##.......",
+            "#...#...#..",
+            ".#....#..#.",
+            "..#.#...#.#",
+            ".#...##..#.",
+            "..#.##.....",
+            ".#.#.#....#",
+            ".#........#",
+            "#.##...#...",
+            "#...##....#",
+            ".#..#...#.#",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let map = TobogganMap::parse(&lines).expect("Could not parse map.");
+        let counts: Vec<usize> = PART_TWO_SLOPES
+            .iter()
+            .map(|&slope| map.count_trees(slope))
+            .collect();
+        assert_eq!(counts, vec![2, 7, 3, 4, 2]);
+
+        let slope = Slope { right: 3, down: 1 };
+        let path = map.trace(slope);
+        assert_eq!(path.cells[..3], [(1, 3), (2, 6), (3, 9)]);
+        assert_eq!(path.trees[..2], [(2, 6), (4, 1)]);
+        let rendered = map.render_path(slope);
+        let rendered: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            rendered[..4],
+            ["..##.......", "#..O#...#..", ".#....X..#.", "..#.#...#O#"]
+        );
+
+        // Going left three is the same as going right eight on an 11-wide map.
+        let left = map.trace(Slope { right: -3, down: 1 });
+        assert_eq!(left, map.trace(Slope { right: 8, down: 1 }));
+        assert_eq!(left.cells[0], (1, 8));
+
+        let flat = Slope { right: 1, down: 0 };
+        assert_eq!(map.trace(flat).cells, vec![]);
+        assert_eq!(map.count_trees(flat), 0);
+        assert_eq!(
+            map.render_path(flat).lines().nth(1),
+            Some(lines[1].as_str())
+        );
+
+        assert!(TobogganMap::parse(&["..#".to_string(), ".#".to_string()]).is_err());
+        assert!(TobogganMap::parse(&["..x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn day_three_slope_search() {
+        use aoc::day_three::{Slope, SlopeSearch, TobogganMap};
+
+        let map = TobogganMap::from_file("./misc/D03.txt");
+        let search = SlopeSearch::new(&map, 40, 4);
+        for slope in search.slopes() {
+            assert_eq!(search.count(slope), Some(map.count_trees(slope)));
+        }
+        let brute_fewest = search
+            .slopes()
+            .map(|s| map.count_trees(s))
+            .min()
+            .expect("No slopes.");
+        let brute_most = search
+            .slopes()
+            .map(|s| map.count_trees(s))
+            .max()
+            .expect("No slopes.");
+        let (fewest, fewest_count) = search.fewest_trees().expect("No slopes.");
+        let (most, most_count) = search.most_trees().expect("No slopes.");
+        assert_eq!(fewest_count, brute_fewest);
+        assert_eq!(map.count_trees(fewest), brute_fewest);
+        assert_eq!(most_count, brute_most);
+        assert_eq!(map.count_trees(most), brute_most);
+
+        let (slopes, product) = search.min_product(3).expect("Not enough slopes.");
+        assert_eq!(slopes.len(), 3);
+        let mut counts: Vec<usize> = search.slopes().map(|s| map.count_trees(s)).collect();
+        counts.sort_unstable();
+        assert_eq!(product, counts[..3].iter().map(|&c| c as u128).product());
+        assert_eq!(search.min_product(1000), None);
+
+        let small = SlopeSearch::new(&map, 3, 1);
+        assert_eq!(small.count(Slope { right: 3, down: 1 }), Some(220));
+        assert_eq!(small.count(Slope { right: 4, down: 1 }), None);
+        assert_eq!(small.count(Slope { right: 1, down: 2 }), None);
+        assert_eq!(small.count(Slope { right: 0, down: 1 }), None);
+
+        let empty = SlopeSearch::new(&TobogganMap::parse(&[]).unwrap(), 3, 2);
+        assert_eq!(empty.slopes().count(), 0);
+        assert_eq!(empty.count(Slope { right: 1, down: 2 }), Some(0));
+        assert_eq!(empty.fewest_trees(), None);
+        assert_eq!(empty.most_trees(), None);
+        assert_eq!(empty.min_product(2), None);
+    }
+
     #[test]
     fn day_four() {
         let filename = "./misc/D04.txt";
@@ -1847,13 +5375,330 @@ mod tests {
         assert_eq!(p2, 188);
     }
 
+    #[test]
+    fn day_four_schema() {
+        use aoc::day_four::{Schema, Validator};
+
+        let schema = Schema::part_two();
+        let invalid = [
+            "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+            "iyr:2019 hcl:#602927 eyr:1967 hgt:170cm ecl:grn pid:012533040 byr:1946",
+            "hcl:dab227 iyr:2012 ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277",
+            "hgt:59cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 pid:3556412378 byr:2007",
+        ];
+        let valid = [
+            "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f",
+            "eyr:2029 ecl:blu cid:129 byr:1989 iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm",
+            "hcl:#888785 hgt:164cm byr:2001 iyr:2015 cid:88 pid:545766238 ecl:hzl eyr:2022",
+            "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719",
+        ];
+        assert!(invalid.iter().all(|p| !schema.is_valid(p)));
+        assert!(valid.iter().all(|p| schema.is_valid(p)));
+        assert!(!schema.is_valid(&format!("{} byr:1980", valid[0])));
+        assert!(!schema.is_valid(&format!("{} xyz:1", valid[0])));
+        assert!(Schema::part_one().is_valid(invalid[0]));
+
+        let custom = Schema::parse(
+            "# relaxed rules for a test run\n\
+             name required one-of alice bob\n\
+             \n\
+             hgt optional units cm:100-250 m:1-2  # metres too\n\
+             code required digits 4\n",
+        )
+        .expect("Could not parse schema.");
+        assert_eq!(
+            custom.rule("hgt").map(|r| r.validator.clone()),
+            Some(Validator::UnitRange(vec![
+                ("cm".to_string(), 100, 250),
+                ("m".to_string(), 1, 2)
+            ]))
+        );
+        assert!(custom.is_valid("name:bob code:0042"));
+        assert!(custom.is_valid("name:alice\nhgt:2m code:1234"));
+        assert!(!custom.is_valid("name:carol code:1234"));
+        assert!(!custom.is_valid("name:bob hgt:3m code:1234"));
+        assert!(!custom.is_valid("name:bob"));
+
+        assert!(Schema::parse("byr required range 1920").is_err());
+        assert!(Schema::parse("byr sometimes any").is_err());
+        assert!(Schema::parse("byr required colour").is_err());
+        assert!(Schema::parse("hgt required units cm150").is_err());
+        assert!(Schema::parse("byr required any\nbyr optional any").is_err());
+    }
+
+    #[test]
+    fn day_four_diagnostics() {
+        use aoc::day_four::{diagnose_str, Diagnostic, DiagnosticKind, Schema};
+
+        let contents = "\
+ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
+byr:1937 iyr:2017 cid:147 hgt:183cm
+
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
+hcl:#cfa07d byr:1929 byr:1930
+
+hcl:#ae17e1 iyr:2013
+eyr:2024 nickname:bob
+ecl:brn pid:7600 byr:1931
+hgt:179 oops
+";
+        let diagnostics = diagnose_str(contents, &Schema::part_two());
+        let at = |record, line, kind| Diagnostic { record, line, kind };
+        assert_eq!(
+            diagnostics,
+            vec![
+                at(2, 5, DiagnosticKind::DuplicateField("byr".to_string())),
+                at(2, 4, DiagnosticKind::MissingField("hgt".to_string())),
+                at(3, 8, DiagnosticKind::UnknownField("nickname".to_string())),
+                at(
+                    3,
+                    9,
+                    DiagnosticKind::MalformedValue {
+                        field: "pid".to_string(),
+                        value: "7600".to_string(),
+                        expected: "9 digits".to_string()
+                    }
+                ),
+                at(
+                    3,
+                    10,
+                    DiagnosticKind::MalformedValue {
+                        field: "hgt".to_string(),
+                        value: "179".to_string(),
+                        expected: "a number followed by cm or in".to_string()
+                    }
+                ),
+                at(3, 10, DiagnosticKind::MalformedField("oops".to_string())),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "record 2 (line 5): duplicate field `byr`"
+        );
+
+        let out_of_range = diagnose_str(
+            "byr:1919 iyr:2015 eyr:2025 hgt:200cm hcl:#123abc ecl:blu pid:99999999999",
+            &Schema::part_two(),
+        );
+        let messages: Vec<String> = out_of_range.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "record 1 (line 1): byr `1919` is out of range, allowed 1920-2002",
+                "record 1 (line 1): hgt `200cm` is out of range, allowed 150-193cm",
+                "record 1 (line 1): pid `99999999999` is malformed, expected 9 digits",
+            ]
+        );
+
+        let diagnostics = aoc::day_four::diagnose("./misc/D04.txt", &Schema::part_two());
+        let mut failing: Vec<usize> = diagnostics.iter().map(|d| d.record).collect();
+        failing.dedup();
+        let records = aoc::records_from_file("./misc/D04.txt").len();
+        assert_eq!(records - failing.len(), 188);
+    }
+
+    #[test]
+    fn day_four_overlapping_units() {
+        use aoc::day_four::{Validator, ValueError};
+
+        let height =
+            Validator::UnitRange(vec![("m".to_string(), 1, 2), ("cm".to_string(), 100, 250)]);
+        assert_eq!(height.check("150cm"), Ok(()));
+        assert_eq!(height.check("2m"), Ok(()));
+        assert_eq!(
+            height.check("300cm"),
+            Err(ValueError::OutOfRange {
+                allowed: "100-250cm".to_string()
+            })
+        );
+        assert_eq!(
+            height.check("3m"),
+            Err(ValueError::OutOfRange {
+                allowed: "1-2m".to_string()
+            })
+        );
+        assert!(height.check("150in").is_err());
+    }
+
+    #[test]
+    fn day_four_formats() {
+        use aoc::day_four::{count_valid_passports, read_passports, Format, Passport, Schema};
+        use itertools::Itertools;
+
+        let passports = read_passports("./misc/D04.txt").unwrap();
+        assert_eq!(count_valid_passports(&passports, &Schema::part_one()), 239);
+        assert_eq!(count_valid_passports(&passports, &Schema::part_two()), 188);
+
+        // CSV puts fields in column order, so compare them sorted.
+        let fields = |ps: &[Passport]| {
+            ps.iter()
+                .map(|p| p.fields.iter().cloned().sorted().collect())
+                .collect::<Vec<Vec<_>>>()
+        };
+        for (format, extension) in [
+            (Format::Records, "txt"),
+            (Format::JsonLines, "jsonl"),
+            (Format::Csv, "csv"),
+        ] {
+            let text = format.write(&passports).unwrap();
+            assert_eq!(fields(&format.parse(&text).unwrap()), fields(&passports));
+
+            let path = std::env::temp_dir().join(format!("aoc_day_four_formats.{}", extension));
+            std::fs::write(&path, text).expect("Could not write file.");
+            let filename = path.to_str().unwrap();
+            assert_eq!(Format::from_path(filename), format);
+            assert_eq!(aoc::day_four::solve(aoc::Problem::Two, filename), 188);
+            std::fs::remove_file(&path).expect("Could not remove file.");
+        }
+
+        let json = Format::JsonLines
+            .parse("{\"hgt\": \"183cm\", \"byr\": 1937, \"cid\": null, \"ecl\": \"g\\u0072y\\\"\"}\n\n")
+            .unwrap();
+        assert_eq!(json[0].line, 1);
+        assert_eq!(
+            json[0].fields,
+            vec![
+                ("hgt".to_string(), "183cm".to_string()),
+                ("byr".to_string(), "1937".to_string()),
+                ("ecl".to_string(), "gry\"".to_string()),
+            ]
+        );
+        assert_eq!(
+            json[0].to_json_line(),
+            "{\"hgt\":\"183cm\",\"byr\":\"1937\",\"ecl\":\"gry\\\"\"}"
+        );
+        assert_eq!(
+            Format::JsonLines.parse("{\"a\":1}\n{\"b\" 2}"),
+            Err("line 2: expected `:` after \"b\"".to_string())
+        );
+
+        let csv = Format::Csv
+            .parse("byr,note\r\n1937,\"a, \"\"b\"\"\nc\"\r\n\r\n,x\r\n")
+            .unwrap();
+        assert_eq!(csv[0].line, 2);
+        assert_eq!(csv[0].get("note"), Some("a, \"b\"\nc"));
+        assert_eq!((csv[1].line, csv[1].get("byr")), (5, None));
+        assert_eq!(
+            Format::Csv.write(&csv).unwrap(),
+            "byr,note\n1937,\"a, \"\"b\"\"\nc\"\n,x\n"
+        );
+        assert_eq!(
+            Format::Csv.parse("a,b\n1\n"),
+            Err("line 2: expected 2 cells, found 1".to_string())
+        );
+        assert!(Format::Records.write(&csv).is_err());
+
+        let valid = "{\"byr\":\"1937\",\"iyr\":\"2017\",\"eyr\":\"2020\",\"hgt\":\"183cm\",\
+                     \"hcl\":\"#fffffd\",\"ecl\":\"gry\",\"pid\":\"860033327\"}";
+        let path = std::env::temp_dir().join("aoc_day_four_bad_line.jsonl");
+        std::fs::write(&path, format!("{}\n{{\"byr\" 1937}}\n{}\n", valid, valid))
+            .expect("Could not write file.");
+        let filename = path.to_str().unwrap();
+        assert_eq!(aoc::day_four::solve(aoc::Problem::Two, filename), 2);
+        assert_eq!(
+            read_passports(filename).map(|p| p.len()),
+            Err("line 2: expected `:` after \"byr\"".to_string())
+        );
+        std::fs::remove_file(&path).expect("Could not remove file.");
+        assert!(read_passports("./misc/missing.csv")
+            .unwrap_err()
+            .starts_with("could not read `./misc/missing.csv`"));
+        assert_eq!(
+            Format::Records.parse("a:1\nb\n"),
+            Err("line 2: `b` is not a `name:value` field".to_string())
+        );
+    }
+
     #[test]
     fn day_five() {
         let filename = "./misc/D05.txt";
         let p1 = aoc::day_five::solve(aoc::Problem::One, filename);
         let p2 = aoc::day_five::solve(aoc::Problem::Two, filename);
-        assert_eq!(p1, 892);
-        assert_eq!(p2, 625);
+        assert_eq!(p1, Some(892));
+        assert_eq!(p2, Some(625));
+    }
+
+    #[test]
+    fn day_five_seat_map() {
+        use aoc::day_five::{BoardingPass, SeatMap};
+
+        let codec = BoardingPass::new(2, 2).unwrap();
+        let codes: Vec<String> = [0, 1, 3, 5, 6, 8, 10, 11, 15]
+            .iter()
+            .map(|&id: &u64| codec.encode(id / 4, id % 4).unwrap())
+            .collect();
+        let seats = SeatMap::new(codec, &codes).unwrap();
+        assert_eq!(seats.render(), "##.#\n.##.\n#.##\n...#\n");
+        let gaps: Vec<u64> = seats.gaps().iter().map(|s| s.id).collect();
+        assert_eq!(gaps, vec![2, 4, 7, 9]);
+        assert_eq!(seats.highest().map(|s| (s.row, s.column)), Some((3, 3)));
+
+        let two_taken = SeatMap::new(codec, &codes[..2]).unwrap();
+        assert!(two_taken.gaps().is_empty());
+        let empty = SeatMap::new(codec, &[]).unwrap();
+        assert_eq!(empty.highest(), None);
+        assert!(SeatMap::new(codec, &["FBX".to_string()]).is_err());
+        assert_eq!(
+            SeatMap::new(BoardingPass::new(40, 20).unwrap(), &[]),
+            Err("a seat map holds at most 24 bits of seats, not 60".to_string())
+        );
+
+        let plane = SeatMap::from_file("./misc/D05.txt", BoardingPass::standard()).unwrap();
+        let gaps: Vec<u64> = plane.gaps().iter().map(|s| s.id).collect();
+        assert_eq!(gaps, vec![625]);
+        assert_eq!(plane.render().lines().count(), 128);
+    }
+
+    #[test]
+    fn day_five_codec() {
+        use aoc::day_five::{BoardingPass, Seat};
+
+        let standard = BoardingPass::standard();
+        assert_eq!(
+            standard.decode("FBFBBFFRLR"),
+            Ok(Seat {
+                row: 44,
+                column: 5,
+                id: 357
+            })
+        );
+        assert_eq!(standard.decode("BBFFBBFRLL").map(|s| s.id), Ok(820));
+        assert_eq!(standard.encode(44, 5), Ok("FBFBBFFRLR".to_string()));
+        for id in 0..1024 {
+            let seat = standard.seat(id).unwrap();
+            let code = standard.encode(seat.row, seat.column).unwrap();
+            assert_eq!(standard.decode(&code), Ok(seat));
+        }
+        assert_eq!(standard.seat(1024), None);
+
+        assert_eq!(
+            standard.decode("FBF"),
+            Err("`FBF` has 3 characters, expected 10".to_string())
+        );
+        assert_eq!(
+            standard.decode("FBFBBLFRLR"),
+            Err("`FBFBBLFRLR` has `L` at position 6, expected F or B".to_string())
+        );
+        assert_eq!(
+            standard.decode("FBFBBFFRLF"),
+            Err("`FBFBBFFRLF` has `F` at position 10, expected L or R".to_string())
+        );
+        assert_eq!(
+            standard.encode(128, 0),
+            Err("row 128 is past the last row 127".to_string())
+        );
+        assert_eq!(
+            standard.encode(0, 8),
+            Err("column 8 is past the last column 7".to_string())
+        );
+
+        let wide = BoardingPass::new(4, 5).unwrap();
+        assert_eq!(wide.encode(9, 17), Ok("BFFBRLLLR".to_string()));
+        assert_eq!(wide.decode("BFFBRLLLR").map(|s| s.id), Ok(9 * 32 + 17));
+        assert!(BoardingPass::new(0, 0).is_err());
+        assert!(BoardingPass::new(40, 24).is_err());
+        assert!(BoardingPass::new(u32::MAX, 1).is_err());
+        assert_eq!((wide.row_bits(), wide.column_bits()), (4, 5));
     }
 
     #[test]
@@ -1865,6 +5710,83 @@ mod tests {
         assert_eq!(p2, 3143);
     }
 
+    #[test]
+    fn day_six_quantifiers() {
+        use aoc::day_six::{count, histogram, letters, Group, Quantifier};
+
+        let groups: Vec<Group> =
+            aoc::records("abc\n\na\nb\nc\n\nab\nac\n\na\na\na\na\n\nb\n\nzz\nz\n")
+                .iter()
+                .map(|r| Group::from_record(r).unwrap())
+                .collect();
+        assert_eq!(count(&groups, Quantifier::Anyone), 12);
+        assert_eq!(count(&groups, Quantifier::Everyone), 7);
+        assert_eq!(count(&groups, Quantifier::AtLeast(2)), 3);
+        assert_eq!(count(&groups, Quantifier::Exactly(1)), 9);
+        assert_eq!(groups[2].line, 7);
+        assert_eq!(letters(groups[2].questions(Quantifier::Exactly(1))), "bc");
+        assert_eq!(letters(groups[2].questions(Quantifier::Everyone)), "a");
+        assert_eq!(groups[2].count(Quantifier::Nobody), 23);
+        assert_eq!(letters(groups[5].questions(Quantifier::Everyone)), "z");
+
+        let mut expected = [0; 26];
+        expected[..3].copy_from_slice(&[8, 4, 3]);
+        expected[25] = 2;
+        assert_eq!(histogram(&groups), expected);
+        assert_eq!(groups[1].histogram()[..4], [1, 1, 1, 0]);
+        assert_eq!(
+            Group::from_record(&aoc::records("ab\naB")[0]),
+            Err("line 2: `B` is not a question".to_string())
+        );
+
+        let groups = aoc::day_six::groups_from_file("./misc/D06.txt").unwrap();
+        assert_eq!(count(&groups, Quantifier::AtLeast(1)), 6351);
+        let everyone: u32 = groups
+            .iter()
+            .map(|g| g.count(Quantifier::Exactly(g.members.len())))
+            .sum();
+        assert_eq!(everyone, 3143);
+    }
+
+    #[test]
+    fn blank_line_records() {
+        let records = aoc::records("\r\n\r\nabc \r\nab\t\r\n \r\n\r\n\r\nb\r\n  \r\na\nc\n\n");
+        let shape: Vec<(usize, usize, usize, String)> = records
+            .iter()
+            .map(|r| (r.number, r.first_line(), r.last_line(), r.text()))
+            .collect();
+        assert_eq!(
+            shape,
+            vec![
+                (1, 3, 4, "abc\nab".to_string()),
+                (2, 8, 8, "b".to_string()),
+                (3, 10, 11, "a\nc".to_string()),
+            ]
+        );
+        assert!(aoc::records(" \n\n").is_empty());
+
+        let path = std::env::temp_dir().join("aoc_blank_line_records.txt");
+        let crlf = std::fs::read_to_string("./misc/D06.txt")
+            .expect("Could not read file.")
+            .replace("\n\n", " \n\n\n")
+            .replace('\n', "\r\n");
+        std::fs::write(&path, crlf).expect("Could not write file.");
+        let filename = path.to_str().unwrap();
+        assert_eq!(aoc::day_six::solve(aoc::Problem::One, filename), 6351);
+        assert_eq!(aoc::day_six::solve(aoc::Problem::Two, filename), 3143);
+        std::fs::remove_file(&path).expect("Could not remove file.");
+
+        let path = std::env::temp_dir().join("aoc_blank_line_records_d04.txt");
+        let crlf = std::fs::read_to_string("./misc/D04.txt")
+            .expect("Could not read file.")
+            .replace("\n\n", "\n \n\n")
+            .replace('\n', "\r\n");
+        std::fs::write(&path, crlf).expect("Could not write file.");
+        let filename = path.to_str().unwrap();
+        assert_eq!(aoc::day_four::solve(aoc::Problem::Two, filename), 188);
+        std::fs::remove_file(&path).expect("Could not remove file.");
+    }
+
     #[test]
     fn day_seven() {
         let filename = "./misc/D07.txt";
@@ -1881,6 +5803,66 @@ mod tests {
         let p2 = aoc::day_eight::solve(aoc::Problem::Two, filename);
         assert_eq!(p1, Some(1528));
         assert_eq!(p2, Some(640));
+        let repair = aoc::day_eight::repair(filename);
+        assert_eq!(
+            repair,
+            Some(aoc::day_eight::Repair {
+                index: 363,
+                accumulator: 640
+            })
+        );
+    }
+
+    #[test]
+    fn day_eight_assembler() {
+        use aoc::day_eight::{assemble, disassemble, ControlFlowGraph, ProgramExit};
+
+        let source = "\
+# spins forever once it reaches the loop
+start:
+    acc +3
+loop_start: nop +0
+    jmp loop_start  # back to the nop
+    acc +1
+";
+        let program = assemble(source).expect("Could not assemble program.");
+        let listing = disassemble(&program);
+        assert_eq!(
+            listing,
+            "acc +3      # 0\n\
+             nop +0      # 1\n\
+             jmp -1      # 2 -> 1\n\
+             acc +1      # 3 (unreachable)\n"
+        );
+        assert_eq!(assemble(&listing), Ok(program.clone()));
+
+        let cfg = ControlFlowGraph::new(&program);
+        assert_eq!(cfg.unreachable(), vec![3]);
+        assert_eq!(cfg.loops(), vec![vec![1, 2]]);
+        assert_eq!(cfg.exit(), ProgramExit::InfiniteLoop(vec![1, 2]));
+        assert!(assemble("jmp nowhere").is_err());
+        assert!(assemble("acc start\nstart: nop +0").is_err());
+
+        let terminating = assemble("jmp done\nacc +1\ndone: jmp +1").unwrap();
+        assert_eq!(
+            ControlFlowGraph::new(&terminating).exit(),
+            ProgramExit::Terminates
+        );
+
+        let far = assemble("nop +0\njmp +2147483647").unwrap();
+        assert_eq!(
+            disassemble(&far),
+            "nop +0      # 0\n\
+             jmp +2147483647 # 1 -> 2147483648 (out of bounds)\n"
+        );
+        assert_eq!(assemble(&disassemble(&far)), Ok(far.clone()));
+        assert_eq!(
+            ControlFlowGraph::new(&far).exit(),
+            ProgramExit::OutOfBounds {
+                line: 1,
+                target: 2147483648
+            }
+        );
     }
 
     #[test]
@@ -1892,6 +5874,63 @@ mod tests {
         assert_eq!(p2, Some(31580383));
     }
 
+    #[test]
+    fn day_nine_example() {
+        use aoc::day_nine::{
+            find_contiguous_range, invalid_numbers, ContiguousRange, InvalidNumberInfo,
+        };
+
+        let numbers = vec![
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309,
+            576,
+        ];
+        let invalid: Vec<InvalidNumberInfo> = invalid_numbers(numbers.clone(), 5).collect();
+        assert_eq!(
+            invalid,
+            vec![InvalidNumberInfo {
+                number: 127,
+                index: 14
+            }]
+        );
+
+        let invalid: Vec<(i64, usize)> = invalid_numbers(vec![1, 2, 3, 5, 9, 14, 4], 2)
+            .map(|info| (info.number, info.index))
+            .collect();
+        assert_eq!(invalid, vec![(9, 4), (4, 6)]);
+
+        let invalid: Vec<(i64, usize)> = invalid_numbers(vec![i64::MAX, 1, 5, 6, i64::MAX], 2)
+            .map(|info| (info.number, info.index))
+            .collect();
+        assert_eq!(invalid, vec![(5, 2), (i64::MAX, 4)]);
+
+        let range = find_contiguous_range(&numbers[..14], 127);
+        assert_eq!(
+            range,
+            Some(ContiguousRange {
+                start: 2,
+                end: 6,
+                min: 15,
+                max: 47
+            })
+        );
+        assert_eq!(find_contiguous_range(&numbers, 3), None);
+
+        let with_negatives = [5, -3, 4, 1, -2];
+        let range = find_contiguous_range(&with_negatives, 3).expect("Range not found.");
+        assert_eq!(
+            (range.start, range.end, range.min, range.max),
+            (2, 5, -2, 4)
+        );
+        let range = find_contiguous_range(&with_negatives, 2).expect("Range not found.");
+        assert_eq!((range.start, range.end), (0, 2));
+
+        let range = find_contiguous_range(&[i64::MAX, 1, 2], 3).expect("Range not found.");
+        assert_eq!((range.start, range.end), (1, 3));
+        let range = find_contiguous_range(&[i64::MAX, -1, 1, 2], 3).expect("Range not found.");
+        assert_eq!((range.start, range.end), (2, 4));
+        assert_eq!(find_contiguous_range(&[i64::MAX, i64::MAX], -2), None);
+    }
+
     #[test]
     fn day_ten() {
         let filename = "./misc/D10.txt";
@@ -1901,6 +5940,76 @@ mod tests {
         assert_eq!(p2, Some(4628074479616));
     }
 
+    #[test]
+    fn day_ten_arrangements() {
+        use aoc::day_ten::{count_arrangements, mandatory_adapters};
+
+        let ratings = vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22];
+        assert_eq!(count_arrangements(&ratings, 3), Some(8));
+        assert_eq!(
+            mandatory_adapters(&ratings, 3),
+            vec![1, 4, 7, 10, 12, 15, 16, 19]
+        );
+        assert_eq!(count_arrangements(&ratings, 1), Some(0));
+        assert_eq!(mandatory_adapters(&ratings, 1), vec![]);
+
+        // A long run of 1-jolt differences and a 2-jolt gap.
+        assert_eq!(count_arrangements(&[0, 1, 2, 3, 4, 5, 8], 3), Some(13));
+        assert_eq!(count_arrangements(&[0, 2, 3, 5, 8], 3), Some(3));
+        assert_eq!(mandatory_adapters(&[0, 2, 3, 5, 8], 3), vec![5]);
+        assert_eq!(count_arrangements(&[0, 1, 2, 3, 4, 5, 6], 4), Some(29));
+
+        // Every rating from 0 to 200 gives a tribonacci number of chains, well past u128.
+        let ratings: Vec<i32> = (0..=200).collect();
+        assert_eq!(count_arrangements(&ratings, 3), None);
+
+        let ratings = aoc::day_ten::joltage_ratings("./misc/D10.txt");
+        assert_eq!(count_arrangements(&ratings, 3), Some(4628074479616));
+    }
+
+    #[test]
+    fn day_ten_chains() {
+        use aoc::day_ten::{chains, longest_chain, sample_chains, shortest_chain};
+
+        let ratings = vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22];
+        let all: Vec<Vec<i32>> = chains(&ratings, 3).collect();
+        assert_eq!(all.len(), 8);
+        assert_eq!(all[0], ratings);
+        assert_eq!(all[7], vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22]);
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(chains(&ratings, 3).take(3).count(), 3);
+        assert_eq!(chains(&ratings, 1).next(), None);
+
+        assert_eq!(shortest_chain(&ratings, 3), Some(all[7].clone()));
+        assert_eq!(longest_chain(&ratings, 3), Some(ratings.clone()));
+        assert_eq!(shortest_chain(&ratings, 1), None);
+
+        let samples: Vec<Vec<i32>> = sample_chains(&ratings, 3, 2020)
+            .expect("Too many chains.")
+            .take(50)
+            .collect();
+        assert!(samples.iter().all(|chain| all.contains(chain)));
+        assert_eq!(
+            samples,
+            sample_chains(&ratings, 3, 2020)
+                .expect("Too many chains.")
+                .take(50)
+                .collect::<Vec<_>>()
+        );
+        assert!(all.iter().all(|chain| samples.contains(chain)));
+        assert_eq!(
+            sample_chains(&ratings, 1, 2020).and_then(|mut s| s.next()),
+            None
+        );
+        let every_rating: Vec<i32> = (0..=200).collect();
+        assert!(sample_chains(&every_rating, 3, 2020).is_none());
+        assert!(chains(&every_rating, 3).next().is_some());
+
+        let ratings = aoc::day_ten::joltage_ratings("./misc/D10.txt");
+        let first = chains(&ratings, 3).next().expect("No chain found.");
+        assert_eq!(first, ratings);
+    }
+
     #[test]
     fn day_eleven() {
         let filename = "./misc/D11.txt";
@@ -1919,6 +6028,200 @@ mod tests {
         assert_eq!(p2, Some(58606));
     }
 
+    #[test]
+    fn day_twelve_navigation() {
+        use aoc::day_twelve::{navigate, Instruction, Navigator, Steering, Vector};
+
+        let example: Vec<Instruction> = ["F10", "N3", "F7", "R90", "F11"]
+            .iter()
+            .map(|x| Instruction::new(x))
+            .collect();
+        let mut ship = Navigator::<isize>::new(Steering::Heading);
+        navigate(&mut ship, &example).unwrap();
+        assert_eq!(
+            (ship.position, ship.manhattan_distance()),
+            (Vector { x: 17, y: -8 }, Some(25))
+        );
+        let mut ship = Navigator::<isize>::new(Steering::Waypoint);
+        navigate(&mut ship, &example).unwrap();
+        assert_eq!(ship.vector, Vector { x: 4, y: -10 });
+        assert_eq!(ship.manhattan_distance(), Some(286));
+
+        let mut ship = Navigator::<isize>::new(Steering::Waypoint);
+        for turn in ["L270", "R450", "L-90", "R450"] {
+            ship.update(&Instruction::new(turn)).unwrap();
+        }
+        assert_eq!(ship.vector, Vector { x: 10, y: 1 });
+
+        let skewed: Vec<Instruction> = ["F10", "L45", "F10", "R45", "N1"]
+            .iter()
+            .map(|x| Instruction::new(x))
+            .collect();
+        let mut ship = Navigator::<isize>::new(Steering::Heading);
+        assert_eq!(
+            navigate(&mut ship, &skewed),
+            Err("instruction 2: `L45` is not a right-angle turn".to_string())
+        );
+        assert_eq!(ship.position, Vector { x: 10, y: 0 });
+
+        let mut ship = Navigator::<f64>::new(Steering::Heading);
+        navigate(&mut ship, &skewed).unwrap();
+        let leg = 10.0 / 2f64.sqrt();
+        assert!((ship.position.x - (10.0 + leg)).abs() < 1e-9);
+        assert!((ship.position.y - (leg + 1.0)).abs() < 1e-9);
+        assert!((ship.vector.x - 1.0).abs() < 1e-9 && ship.vector.y.abs() < 1e-9);
+
+        let mut ship = Navigator::<f64>::new(Steering::Waypoint);
+        ship.update(&Instruction::new("R90")).unwrap();
+        assert_eq!(ship.vector, Vector { x: 1.0, y: -10.0 });
+        ship.strict = true;
+        assert!(ship.update(&Instruction::new("R30")).is_err());
+    }
+
+    #[test]
+    fn day_twelve_route() {
+        use aoc::day_twelve::{record, Instruction, Navigator, Steering, Vector};
+
+        let example: Vec<Instruction> = ["F10", "N3", "F7", "R90", "F11"]
+            .iter()
+            .map(|x| Instruction::new(x))
+            .collect();
+        let route = record(&mut Navigator::<isize>::new(Steering::Waypoint), &example).unwrap();
+        let ships: Vec<(isize, isize)> = route.ships.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(
+            ships,
+            vec![
+                (0, 0),
+                (100, 10),
+                (100, 10),
+                (170, 38),
+                (170, 38),
+                (214, -72)
+            ]
+        );
+        assert_eq!(route.waypoints[2], Vector { x: 110, y: 14 });
+        assert_eq!(route.waypoints[4], Vector { x: 174, y: 28 });
+        assert_eq!(
+            route.bounding_box(),
+            (Vector { x: 0, y: -72 }, Vector { x: 214, y: 38 })
+        );
+        assert_eq!(route.furthest(), Vector { x: 214, y: -72 });
+        let sailed = 10100f64.sqrt() + 5684f64.sqrt() + 14036f64.sqrt();
+        assert!((route.distance_sailed() - sailed).abs() < 1e-9);
+
+        let route = record(
+            &mut Navigator::<isize>::new(Steering::Heading),
+            &example[..2],
+        )
+        .unwrap();
+        assert_eq!(
+            route.to_svg(),
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -4 13 5\">\n",
+                "  <polyline points=\"1,0 11,0 11,-3\" fill=\"none\" stroke=\"grey\" ",
+                "stroke-dasharray=\"4 4\" vector-effect=\"non-scaling-stroke\"/>\n",
+                "  <polyline points=\"0,0 10,0 10,-3\" fill=\"none\" stroke=\"navy\" ",
+                "vector-effect=\"non-scaling-stroke\"/>\n",
+                "  <circle cx=\"0\" cy=\"0\" r=\"0.25\" fill=\"red\"/>\n",
+                "</svg>\n"
+            )
+        );
+
+        let instructions = aoc::day_twelve::instructions_from_file("./misc/D12.txt");
+        let route = record(
+            &mut Navigator::<isize>::new(Steering::Waypoint),
+            &instructions,
+        )
+        .unwrap();
+        assert_eq!(route.ships.len(), instructions.len() + 1);
+        assert_eq!(
+            route.ships.last().unwrap().manhattan_distance(),
+            Some(58606)
+        );
+        assert!(route.furthest().manhattan_distance().unwrap() >= 58606);
+    }
+
+    #[test]
+    fn day_twelve_overflow() {
+        use aoc::day_twelve::{navigate, Instruction, Navigator, Steering, Vector};
+
+        let mut ship = Navigator::<isize>::new(Steering::Waypoint);
+        ship.update(&Instruction::new("F100000000000000")).unwrap();
+        ship.update(&Instruction::new("R3600000000090")).unwrap();
+        assert_eq!(
+            (ship.position, ship.vector),
+            (
+                Vector {
+                    x: 1_000_000_000_000_000,
+                    y: 100_000_000_000_000
+                },
+                Vector { x: 1, y: -10 }
+            )
+        );
+
+        let before = ship.clone();
+        assert_eq!(
+            ship.update(&Instruction::new("F1000000000000000000")),
+            Err("`F1000000000000000000` overflows".to_string())
+        );
+        assert_eq!(ship, before);
+
+        let far: Vec<Instruction> = ["E9223372036854775000", "F1000", "S1000"]
+            .iter()
+            .map(|x| Instruction::new(x))
+            .collect();
+        let mut ship = Navigator::<isize>::new(Steering::Heading);
+        assert_eq!(
+            navigate(&mut ship, &far),
+            Err("instruction 2: `F1000` overflows".to_string())
+        );
+        let mut ship = Navigator::<isize>::new(Steering::Heading);
+        navigate(&mut ship, &[far[0].clone(), far[2].clone()]).unwrap();
+        assert_eq!(ship.manhattan_distance(), None);
+
+        let mut ship = Navigator::<isize>::new(Steering::Waypoint);
+        assert!(ship
+            .update(&Instruction::new("S-9223372036854775808"))
+            .is_err());
+        assert_eq!(ship.vector, Vector { x: 10, y: 1 });
+        let route = aoc::day_twelve::record(
+            &mut Navigator::<isize>::new(Steering::Heading),
+            &[Instruction::new("E9223372036854775807")],
+        )
+        .unwrap();
+        assert_eq!(
+            route.waypoints[1],
+            Vector {
+                x: isize::MAX,
+                y: 0
+            }
+        );
+        let mut ship = Navigator::<isize>::new(Steering::Waypoint);
+        let far: Vec<Instruction> = ["E9223372036854775797", "F1"]
+            .iter()
+            .map(|x| Instruction::new(x))
+            .collect();
+        assert_eq!(
+            aoc::day_twelve::record(&mut ship, &far),
+            Err("instruction 2: the waypoint overflows".to_string())
+        );
+        assert_eq!(ship.position, Vector { x: 0, y: 0 });
+        assert_eq!(
+            ship.vector,
+            Vector {
+                x: isize::MAX,
+                y: 1
+            }
+        );
+
+        let mut ship = Navigator::<f64>::new(Steering::Waypoint);
+        ship.vector.x = 1e300;
+        assert!(ship
+            .update(&Instruction::new("F9223372036854775807"))
+            .is_err());
+        assert_eq!(ship.position, Vector { x: 0.0, y: 0.0 });
+    }
+
     #[test]
     fn day_thirteen() {
         let filename = "./misc/D13.txt";
@@ -1938,6 +6241,174 @@ mod tests {
         assert_eq!(p2, Some(2881082759597));
     }
 
+    #[test]
+    fn day_fourteen_floating_memory() {
+        use aoc::day_fourteen::{Decoder, DecoderChip, FloatingMemory, Instruction, Mask, Pattern};
+
+        let program = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let mut chip = DecoderChip::new(36, Decoder::version_two()).unwrap();
+        chip.run(&program(&[
+            "mask = 000000000000000000000000000000X1001X",
+            "mem[42] = 100",
+            "mask = 00000000000000000000000000000000X0XX",
+            "mem[26] = 1",
+        ]))
+        .unwrap();
+        assert_eq!(chip.sum(), 208);
+        assert_eq!(chip.memory.get(59), Some(100));
+        assert_eq!(chip.memory.get(26), Some(1));
+        assert_eq!(chip.memory.get(0), None);
+
+        // Every address of a 64-bit word floats, which could never be expanded.
+        let mut chip = DecoderChip::new(64, Decoder::version_two()).unwrap();
+        chip.run(&[
+            format!("mask = {}", "X".repeat(64)),
+            "mem[0] = 3".to_string(),
+            format!("mask = 1{}", "X".repeat(63)),
+            "mem[0] = 1".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(chip.sum(), (3 + 1) << 63);
+
+        assert!(Instruction::parse("mask = X1X", 4).is_err());
+        assert!(Instruction::parse("mask = X1Y0", 4).is_err());
+        assert!(Instruction::parse("mem[16] = 1", 4).is_err());
+        assert!(Instruction::parse("mem[x] = 1", 4).is_err());
+        assert!(DecoderChip::new(65, Decoder::version_one()).is_err());
+
+        // Writes before the first mask are stored as they are.
+        for decoder in [Decoder::version_one(), Decoder::version_two()] {
+            let mut chip = DecoderChip::new(36, decoder).unwrap();
+            chip.run(&program(&["mem[1] = 1", "mem[6] = 5"])).unwrap();
+            assert_eq!(chip.sum(), 6);
+            assert_eq!(chip.memory.get(6), Some(5));
+        }
+
+        // Pattern subtraction against brute force on a small word.
+        let mask = |m: &str| Mask::parse(m, 6).unwrap();
+        let writes = [
+            (mask("X0XX1X"), 5, 7),
+            (mask("0XX0X1"), 40, 3),
+            (mask("XXXXXX"), 9, 11),
+            (mask("11X0XX"), 0, 2),
+            (mask("X1X1X1"), 63, 13),
+        ];
+        let mut memory = FloatingMemory::default();
+        let mut brute = [None; 64];
+        for (m, address, value) in writes {
+            let (pattern, value) = Decoder::version_two().decode(&m, address, value);
+            memory.write(pattern, value);
+            for (a, slot) in brute.iter_mut().enumerate() {
+                if pattern.contains(a as u64) {
+                    *slot = Some(value);
+                }
+            }
+            for (a, slot) in brute.iter().enumerate() {
+                assert_eq!(memory.get(a as u64), *slot);
+            }
+            let total: u64 = brute.iter().flatten().sum();
+            assert_eq!(memory.sum(), total as u128);
+        }
+        let covered: u128 = memory.writes.iter().map(|(p, _)| p.address_count()).sum();
+        assert_eq!(covered, 64);
+        assert_eq!(
+            Pattern::new(0b1010, 0b0011).intersect(&Pattern::new(0b0110, 0b1100)),
+            Some(Pattern {
+                fixed: 0b1010,
+                floating: 0
+            })
+        );
+    }
+
+    #[test]
+    fn day_fourteen_emulator() {
+        use aoc::day_fourteen::{Decoder, DecoderChip, Instruction, Mask, Pattern, Step};
+
+        let program: Vec<String> = [
+            "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X",
+            "mem[8] = 11",
+            "mem[7] = 101",
+            "mem[8] = 0",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let mut chip = DecoderChip::new(36, Decoder::version_one()).unwrap();
+        chip.run(&program).unwrap();
+        assert_eq!(chip.sum(), 165);
+        assert_eq!(
+            chip.trace[1],
+            Step {
+                line: 2,
+                instruction: Instruction::Write {
+                    address: 8,
+                    value: 11
+                },
+                addresses: Some(Pattern {
+                    fixed: 8,
+                    floating: 0
+                }),
+                value: Some(73),
+            }
+        );
+        assert_eq!(
+            chip.trace_text(),
+            concat!(
+                "1: mask\n",
+                "2: mem[8] = 11 -> 000000000000000000000000000000001000 (1 addresses) = 73\n",
+                "3: mem[7] = 101 -> 000000000000000000000000000000000111 (1 addresses) = 101\n",
+                "4: mem[8] = 0 -> 000000000000000000000000000000001000 (1 addresses) = 64\n",
+            )
+        );
+        assert_eq!(
+            chip.dump(),
+            concat!(
+                "addresses,count,value\n",
+                "000000000000000000000000000000000111,1,101\n",
+                "000000000000000000000000000000001000,1,64\n",
+            )
+        );
+
+        let mut chip = DecoderChip::new(6, Decoder::version_two()).unwrap();
+        chip.run(&["mask = 0X1X00".to_string(), "mem[3] = 5".to_string()])
+            .unwrap();
+        assert_eq!(chip.dump(), "addresses,count,value\n0X1X11,4,5\n");
+
+        // Masks that clear addresses and flip nothing else.
+        let custom = Decoder::parse("address=ckk").unwrap();
+        let mask = Mask::parse("0011", 4).unwrap();
+        assert_eq!(
+            custom.decode(&mask, 0b1111, 9),
+            (
+                Pattern {
+                    fixed: 0b0011,
+                    floating: 0
+                },
+                9
+            )
+        );
+        assert_eq!(Decoder::parse("v1"), Ok(Decoder::version_one()));
+        assert_eq!(
+            Decoder::parse("value=kkf"),
+            Err("values cannot float".to_string())
+        );
+        assert!(Decoder::parse("address=kk").is_err());
+        assert!(Decoder::parse("address=kkz").is_err());
+        assert!(Decoder::parse("memory=kkk").is_err());
+
+        let mut chip = DecoderChip::new(36, Decoder::version_one()).unwrap();
+        assert_eq!(
+            chip.run(&["mem[1] = 2".to_string(), "nop".to_string()]),
+            Err("line 2: `nop` is not a mask or memory assignment".to_string())
+        );
+
+        let path = std::env::temp_dir().join("aoc_day_fourteen_emulator.txt");
+        std::fs::write(&path, "mem[1] = 2\nnop\n").expect("Could not write file.");
+        let filename = path.to_str().unwrap();
+        assert_eq!(aoc::day_fourteen::solve(aoc::Problem::One, filename), None);
+        std::fs::remove_file(&path).expect("Could not remove file.");
+    }
+
     #[test]
     fn day_fifteen() {
         let filename = "./misc/D15.txt";
@@ -1957,6 +6428,69 @@ mod tests {
         assert_eq!(p2, Some(1515506256421));
     }
 
+    #[test]
+    fn day_sixteen_matching() {
+        use aoc::day_sixteen::{field_assignment, match_fields, AssignmentError};
+
+        assert_eq!(
+            match_fields(&[vec![0, 1], vec![1], vec![0, 1, 2]], 3),
+            Ok(vec![0, 1, 2])
+        );
+        assert_eq!(
+            match_fields(&[vec![0, 1], vec![0, 1], vec![2]], 3),
+            Err(AssignmentError::Ambiguous(vec![0, 1]))
+        );
+        // A spare field makes the column that could take it ambiguous.
+        assert_eq!(
+            match_fields(&[vec![0], vec![1, 2]], 3),
+            Err(AssignmentError::Ambiguous(vec![1]))
+        );
+        let error = match_fields(&[vec![0], vec![0], vec![0, 1]], 2).unwrap_err();
+        assert_eq!(error, AssignmentError::NoAssignment(vec![1]));
+        assert_eq!(
+            error.to_string(),
+            "no valid assignment, unmatched columns: 1"
+        );
+        assert_eq!(
+            AssignmentError::Ambiguous(vec![0, 3]).to_string(),
+            "multiple assignments possible, ambiguous columns: 0, 3"
+        );
+
+        // Twenty columns whose candidates only shrink by one each time.
+        let staircase: Vec<Vec<usize>> = (0..20).map(|c| (0..=c).collect()).collect();
+        let expected: Vec<usize> = (0..20).collect();
+        assert_eq!(match_fields(&staircase, 20), Ok(expected));
+
+        let path = std::env::temp_dir().join("aoc_day_sixteen_matching.txt");
+        let notes = "class: 0-1 or 4-19\nrow: 0-5 or 8-19\nseat: 0-13 or 16-19\n\n\
+your ticket:\n11,12,13\n\nnearby tickets:\n3,9,18\n15,1,5\n5,14,9\n";
+        std::fs::write(&path, notes).expect("Could not write file.");
+        let filename = path.to_str().unwrap();
+        assert_eq!(
+            field_assignment(filename),
+            Ok(vec![
+                "row".to_string(),
+                "class".to_string(),
+                "seat".to_string()
+            ])
+        );
+        // Without the ticket ruling out `seat` for the first column, three assignments fit.
+        let ambiguous = notes.replace("15,1,5\n", "");
+        std::fs::write(&path, ambiguous).expect("Could not write file.");
+        assert_eq!(
+            field_assignment(filename),
+            Err(AssignmentError::Ambiguous(vec![0, 1, 2]))
+        );
+        assert_eq!(aoc::day_sixteen::solve(aoc::Problem::Two, filename), None);
+        std::fs::remove_file(&path).expect("Could not remove file.");
+
+        let labels = field_assignment("./misc/D16.txt").unwrap();
+        assert_eq!(
+            labels.iter().filter(|l| l.starts_with("departure")).count(),
+            6
+        );
+    }
+
     #[test]
     fn day_seventeen() {
         let filename = "./misc/D17.txt";
@@ -1,6 +1,138 @@
+use std::env;
 use untitled::aoc;
 
 fn main() {
+    // `cargo run -- password-policy <policy> <file>` counts the passwords in <file> that are
+    // valid under <policy>, e.g. "and(count-range,[0-9]{1,})".
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 4 && args[1] == "password-policy" {
+        match aoc::day_two::parse_policy(&args[2]) {
+            Ok(policy) => println!("{}", aoc::day_two::count_valid(&args[3], policy.as_ref())),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    // `cargo run -- password-audit <csv|json> <file> <policy>...` prints the verdict and
+    // failure reason of every line under each policy.
+    if args.len() >= 5 && args[1] == "password-audit" {
+        let policies: Result<Vec<_>, String> = args[4..]
+            .iter()
+            .map(|spec| aoc::day_two::parse_policy(spec))
+            .collect();
+        let policies = match policies {
+            Ok(policies) => policies,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        let audit = aoc::day_two::audit(&args[3], &policies);
+        match args[2].as_str() {
+            "csv" => print!("{}", aoc::day_two::audit_to_csv(&audit)),
+            "json" => println!("{}", aoc::day_two::audit_to_json(&audit)),
+            format => eprintln!("Unknown audit format `{}`.", format),
+        }
+        return;
+    }
+
+    // `cargo run -- passport-schema <schema> <file>` counts the passports in <file> that are
+    // valid under the rules in the <schema> file.
+    if args.len() == 4 && args[1] == "passport-schema" {
+        match aoc::day_four::Schema::from_file(&args[2]) {
+            Ok(schema) => println!("{}", aoc::day_four::count_valid(&args[3], &schema)),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    // `cargo run -- passport-diagnose <schema> <file>` prints every problem found in the
+    // passports in <file>, one per line.
+    if args.len() == 4 && args[1] == "passport-diagnose" {
+        match aoc::day_four::Schema::from_file(&args[2]) {
+            Ok(schema) => {
+                for diagnostic in aoc::day_four::diagnose(&args[3], &schema) {
+                    println!("{}", diagnostic);
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    // `cargo run -- passport-convert <records|jsonl|csv> <file>` rewrites the passports in
+    // <file>, whose format is picked from its extension, in the given format.
+    if args.len() == 4 && args[1] == "passport-convert" {
+        let format = match args[2].as_str() {
+            "records" => aoc::day_four::Format::Records,
+            "jsonl" => aoc::day_four::Format::JsonLines,
+            "csv" => aoc::day_four::Format::Csv,
+            format => {
+                eprintln!("Unknown passport format `{}`.", format);
+                return;
+            }
+        };
+        match aoc::day_four::read_passports(&args[3]).and_then(|p| format.write(&p)) {
+            Ok(text) => print!("{}", text),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    // `cargo run -- ship-route <heading|waypoint> <file>` prints the ship's route through the
+    // instructions in <file> as SVG.
+    if args.len() == 4 && args[1] == "ship-route" {
+        use aoc::day_twelve::{instructions_from_file, record, Navigator, Steering};
+        let steering = match args[2].as_str() {
+            "heading" => Steering::Heading,
+            "waypoint" => Steering::Waypoint,
+            steering => {
+                eprintln!("Unknown steering `{}`.", steering);
+                return;
+            }
+        };
+        let instructions = instructions_from_file(&args[3]);
+        match record(&mut Navigator::<f64>::new(steering), &instructions) {
+            Ok(route) => print!("{}", route.to_svg()),
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+
+    // `cargo run -- docking <trace|dump> <decoder> [width] <file>` runs the docking program in
+    // <file> and prints its trace or a CSV dump of memory. <decoder> is `v1`, `v2` or a custom
+    // spec such as "address=ksf value=kkk". Masks and addresses are 36 bits wide by default.
+    if (args.len() == 5 || args.len() == 6) && args[1] == "docking" {
+        use aoc::day_fourteen::{Decoder, DecoderChip};
+        let width = match args.len() {
+            6 => match args[4].parse::<u32>() {
+                Ok(width) => width,
+                Err(_) => {
+                    eprintln!("Unknown docking width `{}`.", args[4]);
+                    return;
+                }
+            },
+            _ => 36,
+        };
+        let program: Vec<String> = match std::fs::read_to_string(&args[args.len() - 1]) {
+            Ok(text) => text.lines().map(|l| l.to_string()).collect(),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+        let chip = Decoder::parse(&args[3])
+            .and_then(|decoder| DecoderChip::new(width, decoder))
+            .and_then(|mut chip| chip.run(&program).map(|_| chip));
+        match (chip, args[2].as_str()) {
+            (Ok(chip), "trace") => print!("{}", chip.trace_text()),
+            (Ok(chip), "dump") => print!("{}", chip.dump()),
+            (Ok(_), output) => eprintln!("Unknown docking output `{}`.", output),
+            (Err(e), _) => eprintln!("{}", e),
+        }
+        return;
+    }
+
     let filename = "./misc/D17.txt";
     let problem = aoc::Problem::Two;
     let a = aoc::day_seventeen::solve(problem, filename);